@@ -0,0 +1,44 @@
+//! Expose the sensor through Linux IIO-style attribute files.
+//!
+//! Usage: iio [--device PATH] [--dir PATH] [--period-ms N]
+//!
+//! Run with `cargo run --example iio --features std -- --dir /tmp/iio:device0`
+//! and then for example `cat /tmp/iio:device0/in_intensity_red_raw` or
+//! `echo 0.152590 > /tmp/iio:device0/in_intensity_scale`.
+extern crate linux_embedded_hal as hal;
+use isl29125::{iio::IioDevice, Isl29125, OperatingMode};
+use std::{env, process, thread, time::Duration};
+
+fn usage() -> ! {
+    eprintln!("Usage: iio [--device PATH] [--dir PATH] [--period-ms N]");
+    process::exit(1);
+}
+
+fn main() {
+    let mut device = String::from("/dev/i2c-1");
+    let mut dir = String::from("/run/isl29125/iio:device0");
+    let mut period = 100;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--device" => device = value(),
+            "--dir" => dir = value(),
+            "--period-ms" => period = value().parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+
+    let dev = hal::I2cdev::new(device).unwrap();
+    let mut sensor = Isl29125::new(dev);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let mut iio = IioDevice::new(sensor, dir).unwrap();
+    loop {
+        if let Err(e) = iio.update() {
+            eprintln!("Update failed: {:?}", e);
+        }
+        thread::sleep(Duration::from_millis(period));
+    }
+}
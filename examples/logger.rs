@@ -0,0 +1,64 @@
+//! Log measurements to CSV or JSON lines files.
+//!
+//! Usage: logger [--device PATH] [--dir PATH] [--prefix NAME] [--json]
+//!               [--period-ms N] [--rotate-bytes N] [--rotate-minutes N]
+//!               [--derived] [--samples N]
+//!
+//! Run with `cargo run --example logger --features std -- --derived`.
+extern crate linux_embedded_hal as hal;
+use isl29125::{
+    logger::{Format, Logger, LoggerConfig, Rotation},
+    timestamp::SystemClock,
+    Isl29125, OperatingMode,
+};
+use std::{env, process, time::Duration};
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: logger [--device PATH] [--dir PATH] [--prefix NAME] [--json] \
+         [--period-ms N] [--rotate-bytes N] [--rotate-minutes N] [--derived] [--samples N]"
+    );
+    process::exit(1);
+}
+
+fn number(value: Option<String>) -> u64 {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| usage())
+}
+
+fn main() {
+    let mut device = String::from("/dev/i2c-1");
+    let mut dir = String::from(".");
+    let mut prefix = String::from("isl29125");
+    let mut config = LoggerConfig::new(Format::Csv);
+    let mut samples = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--device" => device = args.next().unwrap_or_else(|| usage()),
+            "--dir" => dir = args.next().unwrap_or_else(|| usage()),
+            "--prefix" => prefix = args.next().unwrap_or_else(|| usage()),
+            "--json" => config = config.with_format(Format::JsonLines),
+            "--derived" => config = config.with_derived_values(true),
+            "--period-ms" => {
+                config = config.with_period(Duration::from_millis(number(args.next())))
+            }
+            "--rotate-bytes" => config = config.with_rotation(Rotation::Size(number(args.next()))),
+            "--rotate-minutes" => {
+                let minutes = number(args.next());
+                config = config.with_rotation(Rotation::Interval(Duration::from_secs(minutes * 60)))
+            }
+            "--samples" => samples = Some(number(args.next()) as usize),
+            _ => usage(),
+        }
+    }
+
+    let dev = hal::I2cdev::new(device).unwrap();
+    let mut sensor = Isl29125::new(dev);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let mut logger = Logger::new(sensor, SystemClock, config, dir, &prefix);
+    logger.run(samples).unwrap();
+}
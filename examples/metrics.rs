@@ -0,0 +1,99 @@
+//! Export the measurements for Prometheus.
+//!
+//! Usage: metrics [--device PATH] [--textfile PATH | --listen ADDRESS]
+//!                [--period-ms N] [--label NAME=VALUE]...
+//!
+//! With `--textfile`, the metrics are written to a file for the
+//! node_exporter textfile collector after every update. With `--listen`,
+//! they are served over HTTP at `/metrics`.
+//!
+//! Run with `cargo run --example metrics --features std -- --listen 0.0.0.0:9125`.
+extern crate linux_embedded_hal as hal;
+use isl29125::{metrics::Exporter, Isl29125, OperatingMode};
+use std::{
+    env,
+    io::ErrorKind,
+    net::TcpListener,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: metrics [--device PATH] [--textfile PATH | --listen ADDRESS] \
+         [--period-ms N] [--label NAME=VALUE]..."
+    );
+    process::exit(1);
+}
+
+fn main() {
+    let mut device = String::from("/dev/i2c-1");
+    let mut textfile = None;
+    let mut listen = None;
+    let mut period = Duration::from_secs(15);
+    let mut labels = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--device" => device = value(),
+            "--textfile" => textfile = Some(value()),
+            "--listen" => listen = Some(value()),
+            "--period-ms" => {
+                period = Duration::from_millis(value().parse().unwrap_or_else(|_| usage()))
+            }
+            "--label" => {
+                let label = value();
+                let (name, value) = label.split_once('=').unwrap_or_else(|| usage());
+                labels.push((name.to_string(), value.to_string()));
+            }
+            _ => usage(),
+        }
+    }
+    if textfile.is_none() && listen.is_none() {
+        usage();
+    }
+
+    let dev = hal::I2cdev::new(device).unwrap();
+    let mut sensor = Isl29125::new(dev);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let mut exporter = Exporter::new(sensor);
+    for (name, value) in &labels {
+        exporter = exporter.with_label(name, value);
+    }
+    let listener = listen.map(|address| {
+        let listener = TcpListener::bind(address).unwrap();
+        listener.set_nonblocking(true).unwrap();
+        listener
+    });
+
+    loop {
+        if let Err(e) = exporter.update() {
+            eprintln!("Update failed: {:?}", e);
+        }
+        if let Some(path) = &textfile {
+            exporter.write_textfile(path).unwrap();
+        }
+        let next_update = Instant::now() + period;
+        while Instant::now() < next_update {
+            match listener.as_ref().map(|l| l.accept()) {
+                Some(Ok((stream, _))) => {
+                    stream.set_nonblocking(false).unwrap();
+                    stream.set_read_timeout(Some(HTTP_TIMEOUT)).unwrap();
+                    stream.set_write_timeout(Some(HTTP_TIMEOUT)).unwrap();
+                    if let Err(e) = exporter.serve_http(stream) {
+                        eprintln!("HTTP request failed: {}", e);
+                    }
+                }
+                Some(Err(e)) if e.kind() != ErrorKind::WouldBlock => {
+                    eprintln!("Accept failed: {}", e)
+                }
+                _ => thread::sleep(Duration::from_millis(50)),
+            }
+        }
+    }
+}
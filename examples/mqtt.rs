@@ -0,0 +1,78 @@
+//! Publish the measurements over MQTT and announce them to Home Assistant.
+//!
+//! Usage: mqtt [--device PATH] [--host HOST] [--port N] [--client-id ID]
+//!             [--username NAME --password PASSWORD] [--base-topic TOPIC]
+//!             [--discovery-prefix PREFIX] [--name NAME] [--period-s N]
+//!
+//! Run with `cargo run --example mqtt --features std -- --host localhost`.
+extern crate linux_embedded_hal as hal;
+use isl29125::{
+    mqtt::{MqttConfig, Publisher},
+    Isl29125, OperatingMode,
+};
+use std::{env, process, time::Duration};
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: mqtt [--device PATH] [--host HOST] [--port N] [--client-id ID] \
+         [--username NAME --password PASSWORD] [--base-topic TOPIC] \
+         [--discovery-prefix PREFIX] [--name NAME] [--period-s N]"
+    );
+    process::exit(1);
+}
+
+fn main() {
+    let mut device = String::from("/dev/i2c-1");
+    let mut host = String::from("localhost");
+    let mut port = 1883;
+    let mut client_id = String::from("isl29125");
+    let mut username = None;
+    let mut password = None;
+    let mut base_topic = None;
+    let mut discovery_prefix = None;
+    let mut name = None;
+    let mut period = 30;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--device" => device = value(),
+            "--host" => host = value(),
+            "--port" => port = value().parse().unwrap_or_else(|_| usage()),
+            "--client-id" => client_id = value(),
+            "--username" => username = Some(value()),
+            "--password" => password = Some(value()),
+            "--base-topic" => base_topic = Some(value()),
+            "--discovery-prefix" => discovery_prefix = Some(value()),
+            "--name" => name = Some(value()),
+            "--period-s" => period = value().parse().unwrap_or_else(|_| usage()),
+            _ => usage(),
+        }
+    }
+
+    let mut config = MqttConfig::new(&host, &client_id).with_port(port);
+    match (username, password) {
+        (Some(username), Some(password)) => config = config.with_credentials(&username, &password),
+        (None, None) => (),
+        _ => usage(),
+    }
+    if let Some(topic) = base_topic {
+        config = config.with_base_topic(&topic);
+    }
+    if let Some(prefix) = discovery_prefix {
+        config = config.with_discovery_prefix(&prefix);
+    }
+    if let Some(name) = name {
+        config = config.with_device_name(&name);
+    }
+
+    let dev = hal::I2cdev::new(device).unwrap();
+    let mut sensor = Isl29125::new(dev);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let mut publisher = Publisher::new(sensor, config);
+    publisher.connect().unwrap();
+    publisher.publish_discovery().unwrap();
+    publisher.run(Duration::from_secs(period)).unwrap();
+}
@@ -0,0 +1,35 @@
+//! Use the ISL29125 together with an EEPROM (at address 0x50) on the same bus.
+//!
+//! Each driver gets its own bus proxy from the `shared-bus` crate. The
+//! `BusManagerSimple` used here is for single-threaded applications. For
+//! interrupt-based and multi-threaded applications see the other bus managers
+//! in `shared-bus`.
+extern crate linux_embedded_hal as hal;
+use embedded_hal::blocking::i2c;
+use isl29125::{Isl29125, OperatingMode};
+use shared_bus::BusManagerSimple;
+
+const EEPROM_ADDR: u8 = 0x50;
+
+fn main() {
+    let bus = BusManagerSimple::new(hal::I2cdev::new("/dev/i2c-1").unwrap());
+    let mut sensor = Isl29125::new(bus.acquire_i2c());
+    let mut eeprom = bus.acquire_i2c();
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+
+    // Read the measurement scale stored in the first EEPROM byte.
+    let mut scale = [0];
+    i2c::WriteRead::write_read(&mut eeprom, EEPROM_ADDR, &[0x00], &mut scale).unwrap();
+    let scale = u32::from(scale[0].max(1));
+    loop {
+        let m = sensor.read().unwrap();
+        println!(
+            "R: {}, G: {}, B: {}",
+            u32::from(m.red) / scale,
+            u32::from(m.green) / scale,
+            u32::from(m.blue) / scale
+        );
+    }
+}
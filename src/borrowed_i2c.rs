@@ -0,0 +1,47 @@
+use embedded_hal::blocking::i2c;
+
+/// I2C bus borrowed for the lifetime of a driver
+///
+/// `embedded-hal` 0.2 does not implement its I2C traits for `&mut I2C`, so
+/// a mutable reference to a bus cannot be passed to
+/// [`Isl29125::new()`](struct.Isl29125.html#method.new) directly. This
+/// wrapper forwards all transactions to the borrowed bus instead, so that
+/// the bus can be used by other code again once the driver is dropped.
+///
+/// ```no_run
+/// extern crate linux_embedded_hal as hal;
+/// use isl29125::{BorrowedI2c, Isl29125};
+///
+/// # fn main() {
+/// let mut dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+/// {
+///     let mut sensor = Isl29125::new(BorrowedI2c(&mut dev));
+///     let m = sensor.read().unwrap();
+///     println!("R: {}, G: {}, B: {}", m.red, m.green, m.blue);
+/// }
+/// // `dev` can be used again here.
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct BorrowedI2c<'a, I2C>(pub &'a mut I2C);
+
+impl<'a, I2C: i2c::Write> i2c::Write for BorrowedI2c<'a, I2C> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.0.write(address, bytes)
+    }
+}
+
+impl<'a, I2C: i2c::WriteRead> i2c::WriteRead for BorrowedI2c<'a, I2C> {
+    type Error = I2C::Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.0.write_read(address, bytes, buffer)
+    }
+}
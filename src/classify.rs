@@ -0,0 +1,295 @@
+//! Nearest reference color classification.
+//!
+//! A [`Palette`] holds `N` reference colors and finds the one closest to a
+//! measurement using the selected [`Metric`]. It does not allocate, so it
+//! can be used in `no_std` environments:
+//!
+//! ```
+//! use isl29125::classify::{Metric, Palette, Reference};
+//! use isl29125::{Measurement, Resolution};
+//!
+//! let palette = Palette::new(
+//!     [
+//!         Reference::new(1.0, 0.1, 0.1),
+//!         Reference::new(0.1, 1.0, 0.1),
+//!         Reference::new(0.1, 0.1, 1.0),
+//!     ],
+//!     Metric::Ciede2000,
+//! )
+//! .with_exposure_normalization(true);
+//! let m = Measurement::new(400, 3000, 350, Resolution::Bit16);
+//! let classification = palette.classify(&m).unwrap();
+//! assert_eq!(1, classification.index);
+//! ```
+//!
+//! [`Palette`]: struct.Palette.html
+//! [`Metric`]: enum.Metric.html
+
+use crate::color::{
+    delta_e_2000, delta_e_76, normalize_exposure, Lab, WhitePoint, Xyz, SRGB_TO_XYZ,
+};
+use crate::Measurement;
+use libm::sqrtf;
+
+/// Distance metric used to compare colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Metric {
+    /// Euclidean distance between normalized RGB values (default)
+    NormalizedRgb,
+    /// CIE76 color difference in CIE L\*a\*b\*
+    Cie76,
+    /// CIEDE2000 color difference in CIE L\*a\*b\*
+    Ciede2000,
+}
+
+impl Default for Metric {
+    fn default() -> Self {
+        Metric::NormalizedRgb
+    }
+}
+
+/// Reference color
+///
+/// Stored as normalized red, green and blue values where `1.0` corresponds
+/// to the maximum count.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Reference {
+    rgb: [f32; 3],
+}
+
+impl Reference {
+    /// Create a reference color from normalized red, green and blue values.
+    pub const fn new(red: f32, green: f32, blue: f32) -> Self {
+        Reference {
+            rgb: [red, green, blue],
+        }
+    }
+
+    /// Create a reference color from a measurement of a reference sample.
+    pub fn from_measurement(measurement: &Measurement) -> Self {
+        Reference {
+            rgb: measurement.normalized(),
+        }
+    }
+
+    /// Create a reference color from its RGB chromaticity coordinates.
+    ///
+    /// `r` and `g` are the proportions of the red and green counts in the
+    /// sum of all counts, `r = R / (R + G + B)`. Since chromaticity does not
+    /// contain any intensity information, the resulting color is scaled so
+    /// that its largest channel is `1.0` and palettes built from
+    /// chromaticities should use exposure normalization.
+    pub fn from_chromaticity(r: f32, g: f32) -> Self {
+        Reference {
+            rgb: normalize_exposure([r, g, 1.0 - r - g]),
+        }
+    }
+
+    /// Normalized red, green and blue values
+    pub fn rgb(&self) -> [f32; 3] {
+        self.rgb
+    }
+}
+
+/// Result of a classification
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Classification {
+    /// Index of the nearest palette entry
+    pub index: usize,
+    /// Distance to the nearest palette entry in units of the metric
+    pub distance: f32,
+    /// Confidence between `0.0` and `1.0`
+    ///
+    /// Computed as `1 - d1 / d2` where `d1` and `d2` are the distances to the
+    /// nearest and second-nearest entries, so it approaches zero when the
+    /// sample is equally close to two entries. For a palette with a single
+    /// entry it is `1 / (1 + d1)`.
+    pub confidence: f32,
+}
+
+/// Palette of `N` reference colors
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette<const N: usize> {
+    references: [Reference; N],
+    metric: Metric,
+    normalize_exposure: bool,
+}
+
+impl<const N: usize> Palette<N> {
+    /// Create a new palette using the given metric.
+    ///
+    /// Exposure normalization is disabled.
+    pub const fn new(references: [Reference; N], metric: Metric) -> Self {
+        Palette {
+            references,
+            metric,
+            normalize_exposure: false,
+        }
+    }
+
+    /// Enable or disable exposure normalization.
+    ///
+    /// When enabled, the sample and the references are scaled so that their
+    /// largest channel is `1.0` before being compared. This makes the
+    /// classification independent of the light intensity and only the
+    /// proportions between channels matter.
+    pub const fn with_exposure_normalization(mut self, enabled: bool) -> Self {
+        self.normalize_exposure = enabled;
+        self
+    }
+
+    /// Reference colors
+    pub fn references(&self) -> &[Reference; N] {
+        &self.references
+    }
+
+    /// Distance metric
+    pub fn metric(&self) -> Metric {
+        self.metric
+    }
+
+    /// Find the palette entry nearest to a measurement.
+    ///
+    /// Returns `None` if the palette is empty.
+    pub fn classify(&self, measurement: &Measurement) -> Option<Classification> {
+        let sample = self.prepare(measurement.normalized());
+        let mut nearest: Option<(usize, f32)> = None;
+        let mut second = f32::INFINITY;
+        for (index, reference) in self.references.iter().enumerate() {
+            let distance = self.distance(&sample, &self.prepare(reference.rgb));
+            match nearest {
+                Some((_, d1)) if distance >= d1 => second = second.min(distance),
+                _ => {
+                    if let Some((_, d1)) = nearest {
+                        second = d1;
+                    }
+                    nearest = Some((index, distance));
+                }
+            }
+        }
+        nearest.map(|(index, distance)| {
+            let confidence = if N == 1 {
+                1.0 / (1.0 + distance)
+            } else if second > 0.0 {
+                1.0 - distance / second
+            } else {
+                0.0
+            };
+            Classification {
+                index,
+                distance,
+                confidence,
+            }
+        })
+    }
+
+    fn prepare(&self, rgb: [f32; 3]) -> [f32; 3] {
+        if self.normalize_exposure {
+            normalize_exposure(rgb)
+        } else {
+            rgb
+        }
+    }
+
+    fn distance(&self, sample: &[f32; 3], reference: &[f32; 3]) -> f32 {
+        let lab =
+            |rgb: &[f32; 3]| Lab::from_xyz(Xyz::from_rgb(*rgb, &SRGB_TO_XYZ), WhitePoint::D65);
+        match self.metric {
+            Metric::NormalizedRgb => {
+                let d = |i: usize| sample[i] - reference[i];
+                sqrtf(d(0) * d(0) + d(1) * d(1) + d(2) * d(2))
+            }
+            Metric::Cie76 => delta_e_76(&lab(reference), &lab(sample)),
+            Metric::Ciede2000 => delta_e_2000(&lab(reference), &lab(sample)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolution;
+
+    const PRIMARIES: [Reference; 3] = [
+        Reference::new(0.8, 0.1, 0.1),
+        Reference::new(0.1, 0.8, 0.1),
+        Reference::new(0.1, 0.1, 0.8),
+    ];
+
+    fn m(red: u16, green: u16, blue: u16) -> Measurement {
+        Measurement::new(red, green, blue, Resolution::Bit16)
+    }
+
+    #[test]
+    fn empty_palette_does_not_classify() {
+        let palette = Palette::<0>::new([], Metric::NormalizedRgb);
+        assert_eq!(None, palette.classify(&m(1, 2, 3)));
+    }
+
+    #[test]
+    fn exact_match_has_full_confidence() {
+        let samples = [
+            m(52428, 6553, 6553),
+            m(6553, 52428, 6553),
+            m(6553, 6553, 52428),
+        ];
+        let references = [
+            Reference::from_measurement(&samples[0]),
+            Reference::from_measurement(&samples[1]),
+            Reference::from_measurement(&samples[2]),
+        ];
+        for metric in [Metric::NormalizedRgb, Metric::Cie76, Metric::Ciede2000] {
+            let palette = Palette::new(references, metric);
+            let c = palette.classify(&samples[1]).unwrap();
+            assert_eq!(1, c.index);
+            assert_eq!(0.0, c.distance);
+            assert_eq!(1.0, c.confidence);
+        }
+    }
+
+    #[test]
+    fn ambiguous_sample_has_low_confidence() {
+        let palette = Palette::new(PRIMARIES, Metric::NormalizedRgb);
+        let c = palette.classify(&m(29491, 29491, 6553)).unwrap();
+        assert!(c.index == 0 || c.index == 1);
+        assert!(c.confidence < 0.01, "{:?}", c);
+    }
+
+    #[test]
+    fn exposure_normalization_ignores_intensity() {
+        let palette = Palette::new(
+            [Reference::new(1.0, 0.5, 0.5), Reference::new(0.2, 0.1, 0.1)],
+            Metric::Cie76,
+        );
+        let dim_pink = m(6553, 3276, 3276);
+        assert_eq!(1, palette.classify(&dim_pink).unwrap().index);
+        let palette = palette.with_exposure_normalization(true);
+        let c = palette.classify(&dim_pink).unwrap();
+        assert!(c.distance < 0.1, "{:?}", c);
+    }
+
+    #[test]
+    fn can_create_reference_from_chromaticity() {
+        let reference = Reference::from_chromaticity(0.5, 0.25);
+        assert_eq!([1.0, 0.5, 0.5], reference.rgb());
+        let palette = Palette::new(
+            [Reference::from_chromaticity(0.2, 0.6), reference],
+            Metric::Ciede2000,
+        )
+        .with_exposure_normalization(true);
+        let c = palette.classify(&m(2000, 1000, 1000)).unwrap();
+        assert_eq!(1, c.index);
+    }
+
+    #[test]
+    fn single_entry_confidence_decreases_with_distance() {
+        let palette = Palette::new([Reference::new(1.0, 1.0, 1.0)], Metric::NormalizedRgb);
+        let near = palette.classify(&m(65535, 65535, 60000)).unwrap();
+        let far = palette.classify(&m(0, 0, 0)).unwrap();
+        assert!(near.confidence > far.confidence);
+        assert_eq!(0, far.index);
+    }
+}
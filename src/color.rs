@@ -0,0 +1,809 @@
+//! Color space conversions and color differences.
+//!
+//! Measurements are converted to CIE XYZ through a 3x3 matrix applied to the
+//! normalized color counts (see [`Measurement::normalized()`]). By default
+//! the counts are treated as linear sRGB values, which is a reasonable
+//! approximation of the sensor response. For accurate results, a matrix
+//! calibrated for the specific setup (optics, cover glass, etc.) should be
+//! used instead.
+//!
+//! The CIE L\*a\*b\* and L\*u\*v\* conversions are relative to a
+//! [`WhitePoint`]. No chromatic adaptation is performed, so when using a white
+//! point other than D65 together with the default matrix, the XYZ values
+//! should be adapted first.
+//!
+//! For display purposes, measurements can be converted to gamma-encoded
+//! 8-bit sRGB with [`Srgb8`] and from there to [`Hsv`] or [`Hsl`]. To
+//! display a color independently of the light intensity, normalize the
+//! exposure first:
+//!
+//! ```
+//! use isl29125::color::{normalize_exposure, Hsv, Srgb8};
+//! use isl29125::{Measurement, Resolution};
+//!
+//! let dim_orange = Measurement::new(3000, 1500, 0, Resolution::Bit16);
+//! let color = Srgb8::from_linear(normalize_exposure(dim_orange.normalized()));
+//! assert_eq!(Srgb8::new(255, 188, 0), color);
+//! let hsv = Hsv::from(color);
+//! assert_eq!(1.0, hsv.value);
+//! ```
+//!
+//! [`Measurement::normalized()`]: ../struct.Measurement.html#method.normalized
+//! [`WhitePoint`]: enum.WhitePoint.html
+//! [`Srgb8`]: struct.Srgb8.html
+//! [`Hsv`]: struct.Hsv.html
+//! [`Hsl`]: struct.Hsl.html
+
+use crate::Measurement;
+use core::f32::consts::PI;
+use libm::{atan2f, cbrtf, cosf, expf, fabsf, powf, sinf, sqrtf};
+
+/// Linear sRGB to CIE XYZ (D65) conversion matrix
+pub const SRGB_TO_XYZ: [[f32; 3]; 3] = [
+    [0.412_456_4, 0.357_576_1, 0.180_437_5],
+    [0.212_672_9, 0.715_152_2, 0.072_175],
+    [0.019_333_9, 0.119_192, 0.950_304_1],
+];
+
+/// CIE XYZ color
+///
+/// `y` is relative to the maximum count, so full scale on all channels
+/// corresponds to `y = 1.0` with the default conversion matrix.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Xyz {
+    /// X
+    pub x: f32,
+    /// Y (luminance)
+    pub y: f32,
+    /// Z
+    pub z: f32,
+}
+
+impl Xyz {
+    /// CIE standard illuminant D50 white point (`y = 1.0`)
+    pub const D50: Xyz = Xyz {
+        x: 0.964_22,
+        y: 1.0,
+        z: 0.825_21,
+    };
+
+    /// CIE standard illuminant D65 white point (`y = 1.0`)
+    pub const D65: Xyz = Xyz {
+        x: 0.950_47,
+        y: 1.0,
+        z: 1.088_83,
+    };
+
+    /// Convert a measurement using the default conversion matrix.
+    pub fn from_measurement(measurement: &Measurement) -> Self {
+        Self::from_rgb(measurement.normalized(), &SRGB_TO_XYZ)
+    }
+
+    /// CIE 1931 xy chromaticity coordinates
+    ///
+    /// Returns `None` for black.
+    pub fn chromaticity(&self) -> Option<(f32, f32)> {
+        let sum = self.x + self.y + self.z;
+        if sum > 0.0 {
+            Some((self.x / sum, self.y / sum))
+        } else {
+            None
+        }
+    }
+
+    /// Correlated color temperature in kelvin
+    ///
+    /// Computed with McCamy's approximation, which is accurate for colors
+    /// close to the Planckian locus between about 2000K and 12500K.
+    /// Returns `None` for black and for colors that have no meaningful color
+    /// temperature: results outside 1000K to 25000K and chromaticities
+    /// further than 0.05 from the Planckian locus in the CIE 1960 uv diagram.
+    pub fn cct(&self) -> Option<f32> {
+        let (x, y) = self.chromaticity()?;
+        let n = (x - 0.3320) / (0.1858 - y);
+        let cct = ((449.0 * n + 3525.0) * n + 6823.3) * n + 5520.33;
+        if !cct.is_finite() || !(1000.0..=25000.0).contains(&cct) {
+            return None;
+        }
+        // Krystek's approximation of the Planckian locus in CIE 1960 uv
+        let t = cct;
+        let locus_u = (0.860_117_8 + 1.541_182_5e-4 * t + 1.286_412e-7 * t * t)
+            / (1.0 + 8.424_202e-4 * t + 7.081_452e-7 * t * t);
+        let locus_v = (0.317_398_73 + 4.228_062_5e-5 * t + 4.204_817e-8 * t * t)
+            / (1.0 - 2.897_418e-5 * t + 1.614_560_5e-7 * t * t);
+        let d = -2.0 * x + 12.0 * y + 3.0;
+        let (u, v) = (4.0 * x / d, 6.0 * y / d);
+        let du = u - locus_u;
+        let dv = v - locus_v;
+        if du * du + dv * dv > 0.05 * 0.05 {
+            None
+        } else {
+            Some(cct)
+        }
+    }
+
+    /// Convert normalized red, green and blue values using the given matrix.
+    pub fn from_rgb(rgb: [f32; 3], matrix: &[[f32; 3]; 3]) -> Self {
+        let row = |r: &[f32; 3]| r[0] * rgb[0] + r[1] * rgb[1] + r[2] * rgb[2];
+        Xyz {
+            x: row(&matrix[0]),
+            y: row(&matrix[1]),
+            z: row(&matrix[2]),
+        }
+    }
+}
+
+/// Reference white used for the CIE L\*a\*b\* and L\*u\*v\* conversions
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WhitePoint {
+    /// CIE standard illuminant D50 (printing, ICC profiles)
+    D50,
+    /// CIE standard illuminant D65 (sRGB, daylight) (default)
+    D65,
+    /// Measured white point, e.g. of a white reference under the ambient
+    /// light. Its `y` value should be greater than zero.
+    Measured(Xyz),
+}
+
+impl Default for WhitePoint {
+    fn default() -> Self {
+        WhitePoint::D65
+    }
+}
+
+impl WhitePoint {
+    /// Measured white point from a measurement of a white reference
+    /// using the default conversion matrix.
+    pub fn from_measurement(measurement: &Measurement) -> Self {
+        WhitePoint::Measured(Xyz::from_measurement(measurement))
+    }
+
+    /// CIE XYZ values of the white point
+    pub fn xyz(&self) -> Xyz {
+        match self {
+            WhitePoint::D50 => Xyz::D50,
+            WhitePoint::D65 => Xyz::D65,
+            WhitePoint::Measured(xyz) => *xyz,
+        }
+    }
+}
+
+/// CIE L\*a\*b\* color
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Lab {
+    /// Lightness (L\*)
+    pub l: f32,
+    /// Green-red axis (a\*)
+    pub a: f32,
+    /// Blue-yellow axis (b\*)
+    pub b: f32,
+}
+
+impl Lab {
+    /// Convert a measurement using the default conversion matrix.
+    pub fn from_measurement(measurement: &Measurement, white: WhitePoint) -> Self {
+        Self::from_xyz(Xyz::from_measurement(measurement), white)
+    }
+
+    /// Convert from CIE XYZ relative to the given white point.
+    pub fn from_xyz(xyz: Xyz, white: WhitePoint) -> Self {
+        let white = white.xyz();
+        let fx = lab_f(xyz.x / white.x);
+        let fy = lab_f(xyz.y / white.y);
+        let fz = lab_f(xyz.z / white.z);
+        Lab {
+            l: 116.0 * fy - 16.0,
+            a: 500.0 * (fx - fy),
+            b: 200.0 * (fy - fz),
+        }
+    }
+}
+
+/// CIE L\*u\*v\* color
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Luv {
+    /// Lightness (L\*)
+    pub l: f32,
+    /// Green-red axis (u\*)
+    pub u: f32,
+    /// Blue-yellow axis (v\*)
+    pub v: f32,
+}
+
+impl Luv {
+    /// Convert a measurement using the default conversion matrix.
+    pub fn from_measurement(measurement: &Measurement, white: WhitePoint) -> Self {
+        Self::from_xyz(Xyz::from_measurement(measurement), white)
+    }
+
+    /// Convert from CIE XYZ relative to the given white point.
+    pub fn from_xyz(xyz: Xyz, white: WhitePoint) -> Self {
+        let white = white.xyz();
+        let l = 116.0 * lab_f(xyz.y / white.y) - 16.0;
+        let (u, v) = uv(&xyz);
+        let (un, vn) = uv(&white);
+        Luv {
+            l,
+            u: 13.0 * l * (u - un),
+            v: 13.0 * l * (v - vn),
+        }
+    }
+}
+
+/// CIE 1976 u'v' chromaticity coordinates. Black is mapped to `(0, 0)`.
+fn uv(xyz: &Xyz) -> (f32, f32) {
+    let denominator = xyz.x + 15.0 * xyz.y + 3.0 * xyz.z;
+    if denominator == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (4.0 * xyz.x / denominator, 9.0 * xyz.y / denominator)
+    }
+}
+
+fn lab_f(t: f32) -> f32 {
+    const DELTA: f32 = 6.0 / 29.0;
+    if t > DELTA * DELTA * DELTA {
+        cbrtf(t)
+    } else {
+        t / (3.0 * DELTA * DELTA) + 4.0 / 29.0
+    }
+}
+
+/// CIE76 color difference (Euclidean distance in L\*a\*b\*)
+pub fn delta_e_76(reference: &Lab, sample: &Lab) -> f32 {
+    let dl = reference.l - sample.l;
+    let da = reference.a - sample.a;
+    let db = reference.b - sample.b;
+    sqrtf(dl * dl + da * da + db * db)
+}
+
+/// CIE94 color difference
+///
+/// Uses the graphic arts weighting factors (`kL = 1`, `K1 = 0.045`,
+/// `K2 = 0.015`). The difference is not symmetric: the chroma of `reference`
+/// is used for the weighting.
+pub fn delta_e_94(reference: &Lab, sample: &Lab) -> f32 {
+    let c1 = sqrtf(reference.a * reference.a + reference.b * reference.b);
+    let c2 = sqrtf(sample.a * sample.a + sample.b * sample.b);
+    let dl = reference.l - sample.l;
+    let dc = c1 - c2;
+    let da = reference.a - sample.a;
+    let db = reference.b - sample.b;
+    // ΔH² can become slightly negative due to rounding.
+    let dh2 = (da * da + db * db - dc * dc).max(0.0);
+    let s_c = 1.0 + 0.045 * c1;
+    let s_h = 1.0 + 0.015 * c1;
+    sqrtf(dl * dl + (dc / s_c) * (dc / s_c) + dh2 / (s_h * s_h))
+}
+
+/// CIEDE2000 color difference
+///
+/// Uses the parametric weighting factors `kL = kC = kH = 1`.
+pub fn delta_e_2000(reference: &Lab, sample: &Lab) -> f32 {
+    let (l1, a1, b1) = (reference.l, reference.a, reference.b);
+    let (l2, a2, b2) = (sample.l, sample.a, sample.b);
+    let pow7 = |v: f32| powf(v, 7.0);
+    let c_mean = (sqrtf(a1 * a1 + b1 * b1) + sqrtf(a2 * a2 + b2 * b2)) / 2.0;
+    let g = 0.5 * (1.0 - sqrtf(pow7(c_mean) / (pow7(c_mean) + pow7(25.0))));
+    let a1p = (1.0 + g) * a1;
+    let a2p = (1.0 + g) * a2;
+    let c1p = sqrtf(a1p * a1p + b1 * b1);
+    let c2p = sqrtf(a2p * a2p + b2 * b2);
+    let hue = |b: f32, a: f32| {
+        if a == 0.0 && b == 0.0 {
+            0.0
+        } else {
+            let h = atan2f(b, a);
+            if h < 0.0 {
+                h + 2.0 * PI
+            } else {
+                h
+            }
+        }
+    };
+    let h1p = hue(b1, a1p);
+    let h2p = hue(b2, a2p);
+
+    let dlp = l2 - l1;
+    let dcp = c2p - c1p;
+    let dhp = if c1p * c2p == 0.0 {
+        0.0
+    } else if fabsf(h2p - h1p) <= PI {
+        h2p - h1p
+    } else if h2p - h1p > PI {
+        h2p - h1p - 2.0 * PI
+    } else {
+        h2p - h1p + 2.0 * PI
+    };
+    let d_hp = 2.0 * sqrtf(c1p * c2p) * sinf(dhp / 2.0);
+
+    let lp_mean = (l1 + l2) / 2.0;
+    let cp_mean = (c1p + c2p) / 2.0;
+    let hp_mean = if c1p * c2p == 0.0 {
+        h1p + h2p
+    } else if fabsf(h1p - h2p) <= PI {
+        (h1p + h2p) / 2.0
+    } else if h1p + h2p < 2.0 * PI {
+        (h1p + h2p + 2.0 * PI) / 2.0
+    } else {
+        (h1p + h2p - 2.0 * PI) / 2.0
+    };
+
+    let deg = PI / 180.0;
+    let t = 1.0 - 0.17 * cosf(hp_mean - 30.0 * deg)
+        + 0.24 * cosf(2.0 * hp_mean)
+        + 0.32 * cosf(3.0 * hp_mean + 6.0 * deg)
+        - 0.20 * cosf(4.0 * hp_mean - 63.0 * deg);
+    let d_theta = 30.0 * deg * expf(-powf((hp_mean / deg - 275.0) / 25.0, 2.0));
+    let r_c = 2.0 * sqrtf(pow7(cp_mean) / (pow7(cp_mean) + pow7(25.0)));
+    let l50 = (lp_mean - 50.0) * (lp_mean - 50.0);
+    let s_l = 1.0 + 0.015 * l50 / sqrtf(20.0 + l50);
+    let s_c = 1.0 + 0.045 * cp_mean;
+    let s_h = 1.0 + 0.015 * cp_mean * t;
+    let r_t = -sinf(2.0 * d_theta) * r_c;
+
+    let dl = dlp / s_l;
+    let dc = dcp / s_c;
+    let dh = d_hp / s_h;
+    sqrtf(dl * dl + dc * dc + dh * dh + r_t * dc * dh)
+}
+
+/// Scale so that the largest channel is `1.0`. Black is left unchanged.
+///
+/// This removes the intensity information, keeping only the proportions
+/// between channels (hue and saturation).
+pub fn normalize_exposure(rgb: [f32; 3]) -> [f32; 3] {
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    if max > 0.0 {
+        [rgb[0] / max, rgb[1] / max, rgb[2] / max]
+    } else {
+        rgb
+    }
+}
+
+/// Normalized red, green and blue values of a measurement divided by those
+/// of a white reference measured under the same light.
+///
+/// The white reference is mapped to `[1.0, 1.0, 1.0]`. Channels for which
+/// the white reference is zero are set to zero.
+pub fn white_balanced(measurement: &Measurement, white: &Measurement) -> [f32; 3] {
+    let rgb = measurement.normalized();
+    let white = white.normalized();
+    let channel = |i: usize| {
+        if white[i] > 0.0 {
+            rgb[i] / white[i]
+        } else {
+            0.0
+        }
+    };
+    [channel(0), channel(1), channel(2)]
+}
+
+/// Gamma-encoded 8-bit sRGB color, as used by displays and RGB LEDs
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Srgb8 {
+    /// Red
+    pub red: u8,
+    /// Green
+    pub green: u8,
+    /// Blue
+    pub blue: u8,
+}
+
+impl Srgb8 {
+    /// Create a new color.
+    pub const fn new(red: u8, green: u8, blue: u8) -> Self {
+        Srgb8 { red, green, blue }
+    }
+
+    /// Convert a measurement treating the normalized counts as linear sRGB.
+    pub fn from_measurement(measurement: &Measurement) -> Self {
+        Self::from_linear(measurement.normalized())
+    }
+
+    /// Gamma-encode linear sRGB values.
+    ///
+    /// Values outside `[0.0, 1.0]` are clamped.
+    pub fn from_linear(rgb: [f32; 3]) -> Self {
+        let encode = |c: f32| {
+            let c = c.clamp(0.0, 1.0);
+            let encoded = if c <= 0.003_130_8 {
+                12.92 * c
+            } else {
+                1.055 * powf(c, 1.0 / 2.4) - 0.055
+            };
+            (encoded * 255.0 + 0.5) as u8
+        };
+        Srgb8::new(encode(rgb[0]), encode(rgb[1]), encode(rgb[2]))
+    }
+
+    fn to_unit(self) -> [f32; 3] {
+        let unit = |c: u8| f32::from(c) / 255.0;
+        [unit(self.red), unit(self.green), unit(self.blue)]
+    }
+}
+
+#[cfg(feature = "rgb")]
+impl From<Srgb8> for rgb::RGB8 {
+    fn from(color: Srgb8) -> Self {
+        rgb::RGB8::new(color.red, color.green, color.blue)
+    }
+}
+
+#[cfg(feature = "embedded-graphics-core")]
+impl From<Srgb8> for embedded_graphics_core::pixelcolor::Rgb888 {
+    fn from(color: Srgb8) -> Self {
+        embedded_graphics_core::pixelcolor::Rgb888::new(color.red, color.green, color.blue)
+    }
+}
+
+/// Hue, saturation and value color
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hsv {
+    /// Hue in degrees `[0.0, 360.0)`
+    pub hue: f32,
+    /// Saturation `[0.0, 1.0]`
+    pub saturation: f32,
+    /// Value `[0.0, 1.0]`
+    pub value: f32,
+}
+
+impl Hsv {
+    /// Convert from red, green and blue values in `[0.0, 1.0]`.
+    pub fn from_rgb(rgb: [f32; 3]) -> Self {
+        let (hue, max, min) = hue(rgb);
+        Hsv {
+            hue,
+            saturation: if max > 0.0 { (max - min) / max } else { 0.0 },
+            value: max,
+        }
+    }
+}
+
+impl From<Srgb8> for Hsv {
+    fn from(color: Srgb8) -> Self {
+        Self::from_rgb(color.to_unit())
+    }
+}
+
+/// Hue, saturation and lightness color
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Hsl {
+    /// Hue in degrees `[0.0, 360.0)`
+    pub hue: f32,
+    /// Saturation `[0.0, 1.0]`
+    pub saturation: f32,
+    /// Lightness `[0.0, 1.0]`
+    pub lightness: f32,
+}
+
+impl Hsl {
+    /// Convert from red, green and blue values in `[0.0, 1.0]`.
+    pub fn from_rgb(rgb: [f32; 3]) -> Self {
+        let (hue, max, min) = hue(rgb);
+        let lightness = (max + min) / 2.0;
+        let saturation = if max == min {
+            0.0
+        } else {
+            (max - min) / (1.0 - fabsf(2.0 * lightness - 1.0))
+        };
+        Hsl {
+            hue,
+            saturation,
+            lightness,
+        }
+    }
+}
+
+impl From<Srgb8> for Hsl {
+    fn from(color: Srgb8) -> Self {
+        Self::from_rgb(color.to_unit())
+    }
+}
+
+/// Hue in degrees together with the maximum and minimum channel values.
+/// The hue of grays is zero.
+fn hue(rgb: [f32; 3]) -> (f32, f32, f32) {
+    let [r, g, b] = rgb;
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let chroma = max - min;
+    let hue = if chroma == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / chroma)
+    } else if max == g {
+        60.0 * ((b - r) / chroma + 2.0)
+    } else {
+        60.0 * ((r - g) / chroma + 4.0)
+    };
+    let hue = if hue < 0.0 { hue + 360.0 } else { hue };
+    (hue, max, min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolution;
+
+    fn assert_close(expected: f32, actual: f32, tolerance: f32) {
+        assert!(
+            fabsf(expected - actual) <= tolerance,
+            "expected {}, got {}",
+            expected,
+            actual
+        );
+    }
+
+    const fn lab(l: f32, a: f32, b: f32) -> Lab {
+        Lab { l, a, b }
+    }
+
+    // Test data from G. Sharma, W. Wu and E. N. Dalal, "The CIEDE2000
+    // color-difference formula: Implementation notes, supplementary test
+    // data, and mathematical observations", 2005.
+    const CIEDE2000_PAIRS: [(Lab, Lab, f32); 15] = [
+        (
+            lab(50.0, 2.6772, -79.7751),
+            lab(50.0, 0.0, -82.7485),
+            2.0425,
+        ),
+        (
+            lab(50.0, 3.1571, -77.2803),
+            lab(50.0, 0.0, -82.7485),
+            2.8615,
+        ),
+        (
+            lab(50.0, 2.8361, -74.0200),
+            lab(50.0, 0.0, -82.7485),
+            3.4412,
+        ),
+        (lab(50.0, 0.0, 0.0), lab(50.0, -1.0, 2.0), 2.3669),
+        (lab(50.0, -1.0, 2.0), lab(50.0, 0.0, 0.0), 2.3669),
+        (lab(50.0, 2.49, -0.001), lab(50.0, -2.49, 0.0009), 7.1792),
+        (lab(50.0, 2.49, -0.001), lab(50.0, -2.49, 0.0010), 7.1792),
+        (lab(50.0, 2.49, -0.001), lab(50.0, -2.49, 0.0011), 7.2195),
+        (lab(50.0, -0.001, 2.49), lab(50.0, 0.0009, -2.49), 4.8045),
+        (lab(50.0, 2.5, 0.0), lab(50.0, 0.0, -2.5), 4.3065),
+        (lab(50.0, 2.5, 0.0), lab(73.0, 25.0, -18.0), 27.1492),
+        (lab(50.0, 2.5, 0.0), lab(50.0, 3.1736, 0.5854), 1.0000),
+        (
+            lab(60.2574, -34.0099, 36.2677),
+            lab(60.4626, -34.1751, 39.4387),
+            1.2644,
+        ),
+        (
+            lab(63.0109, -31.0961, -5.8663),
+            lab(62.8187, -29.7946, -4.0864),
+            1.2630,
+        ),
+        (
+            lab(22.7233, 20.0904, -46.6940),
+            lab(23.0331, 14.9730, -42.5619),
+            2.0373,
+        ),
+    ];
+
+    // Test data from the colour-science Python package (test suite of
+    // colour.difference.delta_E_CIE1976 and delta_E_CIE1994, graphic arts
+    // weighting) with the inputs rounded to `f32`. Expected values are given
+    // in CIE76, CIE94 order.
+    const COLOUR_SCIENCE_PAIRS: [(Lab, Lab, f32, f32); 3] = [
+        (
+            lab(100.0, 21.572104, 272.2282),
+            lab(100.0, 426.67944, 72.39591),
+            451.7133,
+            83.779_23,
+        ),
+        (
+            lab(100.0, 21.572104, 272.2282),
+            lab(50.0, 426.67944, 72.39591),
+            454.4721,
+            97.565_15,
+        ),
+        (
+            lab(100.0, 21.572104, 272.2282),
+            lab(100.0, 74.05217, 276.4532),
+            52.6499,
+            10.053_93,
+        ),
+    ];
+
+    #[test]
+    fn full_scale_measurement_is_d65_white() {
+        let m = Measurement::new(65535, 65535, 65535, Resolution::Bit16);
+        let xyz = Xyz::from_measurement(&m);
+        assert_close(Xyz::D65.x, xyz.x, 1e-4);
+        assert_close(Xyz::D65.y, xyz.y, 1e-4);
+        assert_close(Xyz::D65.z, xyz.z, 1e-4);
+        let lab = Lab::from_measurement(&m, WhitePoint::D65);
+        assert_close(100.0, lab.l, 1e-2);
+        assert_close(0.0, lab.a, 1e-2);
+        assert_close(0.0, lab.b, 1e-2);
+        let luv = Luv::from_measurement(&m, WhitePoint::D65);
+        assert_close(100.0, luv.l, 1e-2);
+        assert_close(0.0, luv.u, 1e-2);
+        assert_close(0.0, luv.v, 1e-2);
+    }
+
+    #[test]
+    fn can_convert_srgb_red() {
+        let xyz = Xyz::from_rgb([1.0, 0.0, 0.0], &SRGB_TO_XYZ);
+        let lab = Lab::from_xyz(xyz, WhitePoint::D65);
+        assert_close(53.24, lab.l, 0.05);
+        assert_close(80.09, lab.a, 0.05);
+        assert_close(67.20, lab.b, 0.05);
+        let luv = Luv::from_xyz(xyz, WhitePoint::D65);
+        assert_close(53.24, luv.l, 0.05);
+        assert_close(175.02, luv.u, 0.05);
+        assert_close(37.76, luv.v, 0.05);
+        let luv = Luv::from_xyz(xyz, WhitePoint::D50);
+        assert_close(167.18, luv.u, 0.05);
+        assert_close(24.10, luv.v, 0.05);
+    }
+
+    #[test]
+    fn black_is_converted_to_zero() {
+        let black = Measurement::new(0, 0, 0, Resolution::Bit16);
+        assert_eq!(
+            Lab::default(),
+            Lab::from_measurement(&black, WhitePoint::D50)
+        );
+        assert_eq!(
+            Luv::default(),
+            Luv::from_measurement(&black, WhitePoint::D50)
+        );
+    }
+
+    #[test]
+    fn measured_white_point_maps_to_neutral() {
+        let white = Measurement::new(30000, 40000, 20000, Resolution::Bit16);
+        let white_point = WhitePoint::from_measurement(&white);
+        let half = Measurement::new(15000, 20000, 10000, Resolution::Bit16);
+        let lab = Lab::from_measurement(&half, white_point);
+        assert_close(0.0, lab.a, 1e-3);
+        assert_close(0.0, lab.b, 1e-3);
+        let luv = Luv::from_measurement(&half, white_point);
+        assert_close(lab.l, luv.l, 1e-3);
+        assert_close(0.0, luv.u, 1e-3);
+        assert_close(0.0, luv.v, 1e-3);
+    }
+
+    #[test]
+    fn delta_e_of_same_color_is_zero() {
+        let lab = lab(50.0, 2.6772, -79.7751);
+        assert_eq!(0.0, delta_e_76(&lab, &lab));
+        assert_eq!(0.0, delta_e_94(&lab, &lab));
+        assert_eq!(0.0, delta_e_2000(&lab, &lab));
+    }
+
+    #[test]
+    fn matches_delta_e_76_reference_data() {
+        for (reference, sample, expected, _) in COLOUR_SCIENCE_PAIRS.iter() {
+            assert_close(*expected, delta_e_76(reference, sample), 1e-3);
+            assert_close(*expected, delta_e_76(sample, reference), 1e-3);
+        }
+    }
+
+    #[test]
+    fn matches_delta_e_94_reference_data() {
+        for (reference, sample, _, expected) in COLOUR_SCIENCE_PAIRS.iter() {
+            assert_close(*expected, delta_e_94(reference, sample), 1e-3);
+        }
+    }
+
+    #[test]
+    fn matches_delta_e_2000_reference_data() {
+        for (reference, sample, expected) in CIEDE2000_PAIRS.iter() {
+            assert_close(*expected, delta_e_2000(reference, sample), 1e-3);
+            assert_close(*expected, delta_e_2000(sample, reference), 1e-3);
+        }
+    }
+
+    #[test]
+    fn can_convert_to_srgb8() {
+        let m = |r, g, b| Measurement::new(r, g, b, Resolution::Bit12);
+        assert_eq!(
+            Srgb8::new(255, 255, 255),
+            Srgb8::from_measurement(&m(4095, 4095, 4095))
+        );
+        assert_eq!(Srgb8::new(0, 0, 0), Srgb8::from_measurement(&m(0, 0, 0)));
+        assert_eq!(
+            Srgb8::new(188, 0, 0),
+            Srgb8::from_measurement(&m(2048, 0, 0))
+        );
+        assert_eq!(Srgb8::new(1, 0, 0), Srgb8::from_linear([0.0003, -1.0, 0.0]));
+    }
+
+    #[test]
+    fn exposure_normalization_tracks_hue_only() {
+        let bright = Measurement::new(40000, 20000, 10000, Resolution::Bit16);
+        let dim = Measurement::new(400, 200, 100, Resolution::Bit16);
+        let bright = Srgb8::from_linear(normalize_exposure(bright.normalized()));
+        let dim = Srgb8::from_linear(normalize_exposure(dim.normalized()));
+        assert_eq!(bright, dim);
+        assert_eq!(255, bright.red);
+        assert_eq!([0.0; 3], normalize_exposure([0.0; 3]));
+    }
+
+    #[test]
+    fn can_white_balance() {
+        let white = Measurement::new(20000, 40000, 10000, Resolution::Bit16);
+        let gray = Measurement::new(10000, 20000, 5000, Resolution::Bit16);
+        assert_eq!([0.5, 0.5, 0.5], white_balanced(&gray, &white));
+        let no_blue = Measurement::new(20000, 40000, 0, Resolution::Bit16);
+        assert_eq!([0.5, 0.5, 0.0], white_balanced(&gray, &no_blue));
+    }
+
+    #[test]
+    fn can_convert_to_hsv_and_hsl() {
+        let orange = Srgb8::new(255, 128, 0);
+        let hsv = Hsv::from(orange);
+        assert_close(30.1, hsv.hue, 0.1);
+        assert_close(1.0, hsv.saturation, 1e-6);
+        assert_close(1.0, hsv.value, 1e-6);
+        let hsl = Hsl::from(orange);
+        assert_close(30.1, hsl.hue, 0.1);
+        assert_close(1.0, hsl.saturation, 1e-6);
+        assert_close(0.5, hsl.lightness, 1e-6);
+
+        let purple = Hsl::from_rgb([0.5, 0.25, 0.75]);
+        assert_close(270.0, purple.hue, 1e-3);
+        assert_close(0.5, purple.saturation, 1e-6);
+        assert_close(0.5, purple.lightness, 1e-6);
+        let magenta = Hsv::from_rgb([0.8, 0.0, 0.4]);
+        assert_close(330.0, magenta.hue, 1e-3);
+
+        let gray = Srgb8::new(100, 100, 100);
+        assert_eq!(0.0, Hsv::from(gray).saturation);
+        assert_eq!(0.0, Hsl::from(gray).saturation);
+        assert_eq!(Hsv::default(), Hsv::from(Srgb8::default()));
+    }
+
+    #[cfg(feature = "rgb")]
+    #[test]
+    fn can_convert_to_rgb8() {
+        assert_eq!(rgb::RGB8::new(1, 2, 3), Srgb8::new(1, 2, 3).into());
+    }
+
+    #[cfg(feature = "embedded-graphics-core")]
+    #[test]
+    fn can_convert_to_rgb888() {
+        use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+        let color: Rgb888 = Srgb8::new(1, 2, 3).into();
+        assert_eq!((1, 2, 3), (color.r(), color.g(), color.b()));
+    }
+
+    #[test]
+    fn can_compute_cct() {
+        let d65 = Xyz::D65.cct().unwrap();
+        assert_close(6504.0, d65, 2.0);
+        let (x, y) = (0.447_57, 0.407_45);
+        let illuminant_a = Xyz {
+            x: x / y,
+            y: 1.0,
+            z: (1.0 - x - y) / y,
+        };
+        assert_close(2856.0, illuminant_a.cct().unwrap(), 5.0);
+        assert_eq!(None, Xyz::default().cct());
+    }
+
+    #[test]
+    fn saturated_colors_have_no_cct() {
+        let blue = Measurement::new(0, 0, 65535, Resolution::Bit16);
+        assert_eq!(None, Xyz::from_measurement(&blue).cct());
+        let on_singularity = Xyz {
+            x: 0.3,
+            y: 0.1858,
+            z: 0.5142,
+        };
+        assert_eq!(None, on_singularity.cct());
+    }
+}
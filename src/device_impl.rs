@@ -1,7 +1,8 @@
 use crate::{
-    BitFlags, Config, ConversionStatus, Error, FaultCount, IRFilteringRange, InterruptPinMode,
-    InterruptThresholdAssignment, Isl29125, Measurement, OperatingMode, Range, Register,
-    Resolution, Status,
+    types::rescale, BitFlags, ChannelCondition, CheckedMeasurement, Config, ConversionStatus,
+    Error, FaultCount, IRFilteringRange, InterruptPinMode, InterruptThresholdAssignment, Isl29125,
+    Measurement, OperatingMode, Range, Register, Resolution, SensorConfig, Status, ThresholdBand,
+    Thresholds,
 };
 use embedded_hal::blocking::i2c;
 
@@ -11,7 +12,9 @@ impl<I2C> Isl29125<I2C> {
         Isl29125 {
             i2c,
             config1: Config { bits: 0 },
+            config2: Config { bits: 0 },
             config3: Config { bits: 0 },
+            thresholds: None,
         }
     }
 
@@ -19,6 +22,27 @@ impl<I2C> Isl29125<I2C> {
     pub fn destroy(self) -> I2C {
         self.i2c
     }
+
+    /// Replace the I2C bus keeping the rest of the driver state.
+    pub(crate) fn swap_i2c<I2C2>(self, i2c: I2C2) -> (Isl29125<I2C2>, I2C) {
+        let Isl29125 {
+            i2c: previous,
+            config1,
+            config2,
+            config3,
+            thresholds,
+        } = self;
+        (
+            Isl29125 {
+                i2c,
+                config1,
+                config2,
+                config3,
+                thresholds,
+            },
+            previous,
+        )
+    }
 }
 
 impl<I2C, E> Isl29125<I2C>
@@ -33,6 +57,52 @@ where
             green: u16::from(data[0]) | (u16::from(data[1]) << 8),
             red: u16::from(data[2]) | (u16::from(data[3]) << 8),
             blue: u16::from(data[4]) | (u16::from(data[5]) << 8),
+            resolution: self.resolution(),
+        })
+    }
+
+    /// Read all colors and check them for saturation and under-range
+    ///
+    /// Each color channel reading is classified according to the current
+    /// resolution. Channels not converted in the current operating mode are
+    /// reported as `ChannelCondition::Disabled`. Additionally, a change of the RGB data sensing range is
+    /// recommended if a channel enabled in the current operating mode is
+    /// saturated at the 375 lux range, or if all enabled channels would fit
+    /// into the 375 lux range while using the 10000 lux range.
+    ///
+    /// If a color channel is saturated at the 10000 lux range,
+    /// `Error::Saturated` is returned since the light level cannot be measured.
+    pub fn read_checked(&mut self) -> Result<CheckedMeasurement, Error<E>> {
+        let measurement = self.read()?;
+        let resolution = measurement.resolution;
+        let range = self.range();
+        let values = [measurement.red, measurement.green, measurement.blue];
+        let enabled = self.operating_mode().enabled_channels();
+        let condition = |i: usize| {
+            if enabled[i] {
+                ChannelCondition::of(values[i], resolution)
+            } else {
+                ChannelCondition::Disabled
+            }
+        };
+        let conditions = [condition(0), condition(1), condition(2)];
+        let any_saturated = conditions.contains(&ChannelCondition::Saturated);
+        // 375 lux is 3.75% of 10000 lux. Leave some margin.
+        let low_range_limit = (u32::from(resolution.max_count()) * 3 / 100) as u16;
+        let fits_low_range =
+            enabled.contains(&true) && (0..3).all(|i| !enabled[i] || values[i] < low_range_limit);
+        let recommended_range = match range {
+            Range::Lux375 if any_saturated => Some(Range::Lux10000),
+            Range::Lux10000 if any_saturated => return Err(Error::Saturated),
+            Range::Lux10000 if fits_low_range => Some(Range::Lux375),
+            _ => None,
+        };
+        Ok(CheckedMeasurement {
+            measurement,
+            red: conditions[0],
+            green: conditions[1],
+            blue: conditions[2],
+            recommended_range,
         })
     }
 
@@ -53,38 +123,21 @@ where
 
     /// Set operating mode
     pub fn set_operating_mode(&mut self, mode: OperatingMode) -> Result<(), Error<E>> {
-        let config1 = self.config1.bits & 0b1111_1000;
-        let mask = match mode {
-            OperatingMode::PowerDown => 0,
-            OperatingMode::GreenOnly => 1,
-            OperatingMode::RedOnly => 2,
-            OperatingMode::BlueOnly => 3,
-            OperatingMode::StandBy => 4,
-            OperatingMode::RedGreenBlue => 5,
-            OperatingMode::RedGreen => 6,
-            OperatingMode::GreenBlue => 7,
-        };
-        self.set_config1(Config {
-            bits: config1 | mask,
-        })
+        self.set_config1(self.config1.with_operating_mode(mode))
     }
 
     /// Set ADC resolution
+    ///
+    /// If interrupt thresholds have been set, they are rescaled to the new
+    /// resolution and written again so that they keep corresponding to the
+    /// same light level.
     pub fn set_resolution(&mut self, resolution: Resolution) -> Result<(), Error<E>> {
-        let config1 = match resolution {
-            Resolution::Bit12 => self.config1.with_high(BitFlags::RESOLUTION),
-            Resolution::Bit16 => self.config1.with_low(BitFlags::RESOLUTION),
-        };
-        self.set_config1(config1)
+        self.set_config1(self.config1.with_resolution(resolution))
     }
 
     /// Set RGB data sensing range
     pub fn set_range(&mut self, range: Range) -> Result<(), Error<E>> {
-        let config1 = match range {
-            Range::Lux375 => self.config1.with_low(BitFlags::RANGE),
-            Range::Lux10000 => self.config1.with_high(BitFlags::RANGE),
-        };
-        self.set_config1(config1)
+        self.set_config1(self.config1.with_range(range))
     }
 
     /// Set IR filtering
@@ -92,22 +145,13 @@ where
     /// The IR adjust value must be in the range `[0-63]`. Providing a
     /// value outside this range will return `Error::InvalidInputData`.
     pub fn set_ir_filtering(&mut self, range: IRFilteringRange) -> Result<(), Error<E>> {
-        let ir_comp = match range {
-            IRFilteringRange::Lower(v) if v > 63 => return Err(Error::InvalidInputData),
-            IRFilteringRange::Lower(v) => v,
-            IRFilteringRange::Higher(v) if v > 63 => return Err(Error::InvalidInputData),
-            IRFilteringRange::Higher(v) => BitFlags::IR_OFFSET | v,
-        };
-        self.write_register(Register::CONFIG2, ir_comp)
+        let ir_comp = ir_filtering_bits(range)?;
+        self.set_config2(Config { bits: ir_comp })
     }
 
     /// Set interrupt pin (INT) mode (Interrupt / Synced conversion start)
     pub fn set_interrupt_pin_mode(&mut self, mode: InterruptPinMode) -> Result<(), Error<E>> {
-        let config1 = match mode {
-            InterruptPinMode::Interrupt => self.config1.with_low(BitFlags::SYNC),
-            InterruptPinMode::SyncStart => self.config1.with_high(BitFlags::SYNC),
-        };
-        self.set_config1(config1)
+        self.set_config1(self.config1.with_interrupt_pin_mode(mode))
     }
 
     /// Set color channel used for threshold value interrupt generation
@@ -115,27 +159,13 @@ where
         &mut self,
         assignment: InterruptThresholdAssignment,
     ) -> Result<(), Error<E>> {
-        let config3 = self.config3.bits & 0b1111_1100;
-        let config3 = match assignment {
-            InterruptThresholdAssignment::None => config3,
-            InterruptThresholdAssignment::Green => config3 | 1,
-            InterruptThresholdAssignment::Red => config3 | 2,
-            InterruptThresholdAssignment::Blue => config3 | 3,
-        };
-        self.set_config3(Config { bits: config3 })
+        self.set_config3(self.config3.with_threshold_assignment(assignment))
     }
 
     /// Set number of consecutive fault events necessary to trigger an interrupt.
     /// This is referred to as "persistence" in the documentation.
     pub fn set_fault_count(&mut self, fault_count: FaultCount) -> Result<(), Error<E>> {
-        let config3 = self.config3.bits & 0b1111_0011;
-        let config3 = match fault_count {
-            FaultCount::One => config3,
-            FaultCount::Two => config3 | (1 << 2),
-            FaultCount::Four => config3 | (2 << 2),
-            FaultCount::Eight => config3 | (3 << 2),
-        };
-        self.set_config3(Config { bits: config3 })
+        self.set_config3(self.config3.with_fault_count(fault_count))
     }
 
     /// Enable generating an interrupt after a conversion is done
@@ -148,9 +178,86 @@ where
         self.set_config3(self.config3.with_low(BitFlags::CONVEN))
     }
 
+    /// Apply a complete configuration
+    ///
+    /// All configuration registers are written, so this is useful to bring a
+    /// sensor into a known state, for example after a power-on or reset.
+    /// The IR adjust value must be in the range `[0-63]`. Providing a
+    /// value outside this range will return `Error::InvalidInputData`.
+    pub fn configure(&mut self, config: &SensorConfig) -> Result<(), Error<E>> {
+        let ir_comp = ir_filtering_bits(config.ir_filtering)?;
+        let config1 = Config::default()
+            .with_operating_mode(config.operating_mode)
+            .with_resolution(config.resolution)
+            .with_range(config.range)
+            .with_interrupt_pin_mode(config.interrupt_pin_mode);
+        let config3 = Config::default()
+            .with_threshold_assignment(config.interrupt_threshold_assignment)
+            .with_fault_count(config.fault_count);
+        let config3 = if config.interrupt_on_conversion_done {
+            config3.with_high(BitFlags::CONVEN)
+        } else {
+            config3
+        };
+        self.set_config1(config1)?;
+        self.set_config2(Config { bits: ir_comp })?;
+        self.set_config3(config3)
+    }
+
     /// Set interrupt thresholds
+    ///
+    /// The thresholds are given in counts at the current resolution. They will
+    /// be rescaled automatically on subsequent resolution changes.
+    ///
+    /// If a threshold is above the maximum count at the current resolution,
+    /// `Error::InvalidInputData` is returned.
     pub fn set_interrupt_thresholds(&mut self, low: u16, high: u16) -> Result<(), Error<E>> {
-        self.write_thresholds(low, high)
+        let max = self.resolution().max_count();
+        if low > max || high > max {
+            return Err(Error::InvalidInputData);
+        }
+        self.write_thresholds(low, high)?;
+        self.thresholds = Some(Thresholds {
+            low,
+            high,
+            resolution: self.resolution(),
+        });
+        Ok(())
+    }
+
+    /// Re-center the interrupt thresholds around the current light level
+    ///
+    /// The channel assigned with
+    /// [`set_interrupt_threshold_assignment()`](#method.set_interrupt_threshold_assignment)
+    /// is read and the thresholds are set to a band around its value, so that
+    /// the next interrupt is only generated once the light level changes
+    /// significantly. The band is at least one count wide on each side and
+    /// limited to the counts available at the current resolution.
+    ///
+    /// Call this after each interrupt to be woken up only on changes. Note
+    /// that the interrupt itself is cleared by reading the status.
+    ///
+    /// The channel value read is returned. If no channel is assigned,
+    /// `Error::InvalidInputData` is returned.
+    pub fn recenter_interrupt_thresholds(&mut self, band: ThresholdBand) -> Result<u16, Error<E>> {
+        let value = match self.interrupt_threshold_assignment() {
+            InterruptThresholdAssignment::None => return Err(Error::InvalidInputData),
+            InterruptThresholdAssignment::Red => self.red()?,
+            InterruptThresholdAssignment::Green => self.green()?,
+            InterruptThresholdAssignment::Blue => self.blue()?,
+        };
+        let delta = match band {
+            ThresholdBand::Percent(percent) => {
+                (u32::from(value) * u32::from(percent) / 100).min(u32::from(u16::MAX)) as u16
+            }
+            ThresholdBand::Absolute(counts) => counts,
+        }
+        .max(1);
+        let max = self.resolution().max_count();
+        let low = value.saturating_sub(delta);
+        let high = value.saturating_add(delta).min(max);
+        self.set_interrupt_thresholds(low, high)?;
+        Ok(value)
     }
 
     /// Read the status
@@ -179,6 +286,105 @@ where
         self.write_register(Register::STATUS, 0)
     }
 
+    pub(crate) fn operating_mode(&self) -> OperatingMode {
+        match self.config1.bits & 0b0000_0111 {
+            0 => OperatingMode::PowerDown,
+            1 => OperatingMode::GreenOnly,
+            2 => OperatingMode::RedOnly,
+            3 => OperatingMode::BlueOnly,
+            5 => OperatingMode::RedGreenBlue,
+            6 => OperatingMode::RedGreen,
+            7 => OperatingMode::GreenBlue,
+            _ => OperatingMode::StandBy,
+        }
+    }
+
+    pub(crate) fn range(&self) -> Range {
+        if self.config1.bits & BitFlags::RANGE != 0 {
+            Range::Lux10000
+        } else {
+            Range::Lux375
+        }
+    }
+
+    pub(crate) fn resolution(&self) -> Resolution {
+        if self.config1.bits & BitFlags::RESOLUTION != 0 {
+            Resolution::Bit12
+        } else {
+            Resolution::Bit16
+        }
+    }
+
+    pub(crate) fn interrupt_pin_mode(&self) -> InterruptPinMode {
+        if self.config1.bits & BitFlags::SYNC != 0 {
+            InterruptPinMode::SyncStart
+        } else {
+            InterruptPinMode::Interrupt
+        }
+    }
+
+    pub(crate) fn interrupt_threshold_assignment(&self) -> InterruptThresholdAssignment {
+        match self.config3.bits & 0b0000_0011 {
+            0 => InterruptThresholdAssignment::None,
+            1 => InterruptThresholdAssignment::Green,
+            2 => InterruptThresholdAssignment::Red,
+            _ => InterruptThresholdAssignment::Blue,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn fault_count(&self) -> FaultCount {
+        match (self.config3.bits & 0b0000_1100) >> 2 {
+            0 => FaultCount::One,
+            1 => FaultCount::Two,
+            2 => FaultCount::Four,
+            _ => FaultCount::Eight,
+        }
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn interrupt_on_conversion_done(&self) -> bool {
+        self.config3.bits & BitFlags::CONVEN != 0
+    }
+
+    /// Interrupt thresholds at the current resolution. If they have not been
+    /// set, the full range is returned, which is equivalent to the power-on
+    /// values.
+    #[cfg(feature = "std")]
+    pub(crate) fn interrupt_thresholds(&self) -> (u16, u16) {
+        let resolution = self.resolution();
+        match self.thresholds {
+            Some(t) => (
+                rescale(t.low, t.resolution, resolution),
+                rescale(t.high, t.resolution, resolution),
+            ),
+            None => (0, resolution.max_count()),
+        }
+    }
+
+    pub(crate) fn ir_filtering(&self) -> IRFilteringRange {
+        let value = self.config2.bits & 0b0011_1111;
+        if self.config2.bits & BitFlags::IR_OFFSET != 0 {
+            IRFilteringRange::Higher(value)
+        } else {
+            IRFilteringRange::Lower(value)
+        }
+    }
+
+    /// Restart the ADC conversion by writing the current configuration again.
+    ///
+    /// This does not start a conversion in `InterruptPinMode::SyncStart`,
+    /// where conversions start on the rising edge at the INT pin.
+    pub(crate) fn restart_conversion(&mut self) -> Result<(), Error<E>> {
+        self.set_config1(self.config1)
+    }
+
+    fn set_config2(&mut self, config2: Config) -> Result<(), Error<E>> {
+        self.write_register(Register::CONFIG2, config2.bits)?;
+        self.config2 = config2;
+        Ok(())
+    }
+
     fn set_config3(&mut self, config3: Config) -> Result<(), Error<E>> {
         self.write_register(Register::CONFIG3, config3.bits)?;
         self.config3 = config3;
@@ -186,9 +392,17 @@ where
     }
 
     fn set_config1(&mut self, config1: Config) -> Result<(), Error<E>> {
+        let previous_resolution = self.resolution();
         self.write_register(Register::CONFIG1, config1.bits)?;
         self.config1 = config1;
-        Ok(())
+        let resolution = self.resolution();
+        match self.thresholds {
+            Some(t) if resolution != previous_resolution => self.write_thresholds(
+                rescale(t.low, t.resolution, resolution),
+                rescale(t.high, t.resolution, resolution),
+            ),
+            _ => Ok(()),
+        }
     }
 
     /// Get device ID (`0x7D`)
@@ -202,6 +416,15 @@ where
     }
 }
 
+fn ir_filtering_bits<E>(range: IRFilteringRange) -> Result<u8, Error<E>> {
+    match range {
+        IRFilteringRange::Lower(v) if v > 63 => Err(Error::InvalidInputData),
+        IRFilteringRange::Lower(v) => Ok(v),
+        IRFilteringRange::Higher(v) if v > 63 => Err(Error::InvalidInputData),
+        IRFilteringRange::Higher(v) => Ok(BitFlags::IR_OFFSET | v),
+    }
+}
+
 impl Config {
     fn with_high(self, mask: u8) -> Self {
         Config {
@@ -213,4 +436,63 @@ impl Config {
             bits: self.bits & !mask,
         }
     }
+
+    fn with_operating_mode(self, mode: OperatingMode) -> Self {
+        let mask = match mode {
+            OperatingMode::PowerDown => 0,
+            OperatingMode::GreenOnly => 1,
+            OperatingMode::RedOnly => 2,
+            OperatingMode::BlueOnly => 3,
+            OperatingMode::StandBy => 4,
+            OperatingMode::RedGreenBlue => 5,
+            OperatingMode::RedGreen => 6,
+            OperatingMode::GreenBlue => 7,
+        };
+        Config {
+            bits: (self.bits & 0b1111_1000) | mask,
+        }
+    }
+
+    fn with_resolution(self, resolution: Resolution) -> Self {
+        match resolution {
+            Resolution::Bit12 => self.with_high(BitFlags::RESOLUTION),
+            Resolution::Bit16 => self.with_low(BitFlags::RESOLUTION),
+        }
+    }
+
+    fn with_range(self, range: Range) -> Self {
+        match range {
+            Range::Lux375 => self.with_low(BitFlags::RANGE),
+            Range::Lux10000 => self.with_high(BitFlags::RANGE),
+        }
+    }
+
+    fn with_interrupt_pin_mode(self, mode: InterruptPinMode) -> Self {
+        match mode {
+            InterruptPinMode::Interrupt => self.with_low(BitFlags::SYNC),
+            InterruptPinMode::SyncStart => self.with_high(BitFlags::SYNC),
+        }
+    }
+
+    fn with_threshold_assignment(self, assignment: InterruptThresholdAssignment) -> Self {
+        let bits = self.bits & 0b1111_1100;
+        let bits = match assignment {
+            InterruptThresholdAssignment::None => bits,
+            InterruptThresholdAssignment::Green => bits | 1,
+            InterruptThresholdAssignment::Red => bits | 2,
+            InterruptThresholdAssignment::Blue => bits | 3,
+        };
+        Config { bits }
+    }
+
+    fn with_fault_count(self, fault_count: FaultCount) -> Self {
+        let bits = self.bits & 0b1111_0011;
+        let bits = match fault_count {
+            FaultCount::One => bits,
+            FaultCount::Two => bits | (1 << 2),
+            FaultCount::Four => bits | (2 << 2),
+            FaultCount::Eight => bits | (3 << 2),
+        };
+        Config { bits }
+    }
 }
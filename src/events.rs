@@ -0,0 +1,419 @@
+//! Software threshold windows and event detection.
+//!
+//! The hardware provides a single window comparator on one color channel
+//! with a fault count of 1, 2, 4 or 8. An [`EventDetector`] evaluates `N`
+//! independent [`Window`]s in software on any color channel, on ratios
+//! between channels, on the illuminance and on the correlated color
+//! temperature, each with its own hysteresis and persistence.
+//!
+//! ```
+//! use isl29125::events::{Channel, EventDetector, Quantity, Window, Zone};
+//! use isl29125::{Measurement, Range, Resolution};
+//!
+//! let mut detector = EventDetector::new([
+//!     Window::new(Quantity::Lux, 50.0, 300.0).with_hysteresis(10.0),
+//!     Window::new(Quantity::Ratio(Channel::Red, Channel::Green), 0.5, 2.0).with_persistence(3),
+//! ]);
+//! let bright = Measurement::new(60000, 60000, 60000, Resolution::Bit16);
+//! let events = detector.process(&bright, Range::Lux375);
+//! assert_eq!(Some(Zone::Above), events[0].map(|e| e.to));
+//! assert_eq!(None, events[1]);
+//! ```
+//!
+//! [`EventDetector`]: struct.EventDetector.html
+//! [`Window`]: struct.Window.html
+
+use crate::color::Xyz;
+use crate::{Error, InterruptThresholdAssignment, Isl29125, Measurement, Range, Resolution};
+use embedded_hal::blocking::i2c;
+
+/// Color channel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channel {
+    /// Red
+    Red,
+    /// Green
+    Green,
+    /// Blue
+    Blue,
+}
+
+/// Quantity monitored by a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Quantity {
+    /// Color channel counts at 16-bit scale, so that the thresholds do not
+    /// depend on the ADC resolution
+    Channel(Channel),
+    /// Ratio between two color channels (numerator, denominator)
+    ///
+    /// Readings where the denominator is zero are ignored.
+    Ratio(Channel, Channel),
+    /// Approximate illuminance in lux
+    Lux,
+    /// Correlated color temperature in kelvin
+    ///
+    /// Black readings are ignored.
+    Cct,
+}
+
+impl Quantity {
+    /// Value of the quantity for a measurement taken at the given range
+    ///
+    /// Returns `None` if the quantity cannot be computed for the measurement.
+    pub fn value(&self, measurement: &Measurement, range: Range) -> Option<f32> {
+        let m = measurement.to_resolution(Resolution::Bit16);
+        let channel = |c: Channel| {
+            f32::from(match c {
+                Channel::Red => m.red,
+                Channel::Green => m.green,
+                Channel::Blue => m.blue,
+            })
+        };
+        match *self {
+            Quantity::Channel(c) => Some(channel(c)),
+            Quantity::Ratio(numerator, denominator) => {
+                let denominator = channel(denominator);
+                if denominator > 0.0 {
+                    Some(channel(numerator) / denominator)
+                } else {
+                    None
+                }
+            }
+            Quantity::Lux => Some(m.lux(range)),
+            Quantity::Cct => Xyz::from_measurement(&m).cct(),
+        }
+    }
+}
+
+/// Zone of a value relative to a window
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Zone {
+    /// Below the low threshold
+    Below,
+    /// Between the thresholds (default)
+    Inside,
+    /// Above the high threshold
+    Above,
+}
+
+impl Default for Zone {
+    fn default() -> Self {
+        Zone::Inside
+    }
+}
+
+/// Threshold window on a quantity
+///
+/// A value enters the `Above` zone when it exceeds the high threshold and
+/// returns to the `Inside` zone when it falls below the high threshold minus
+/// the hysteresis. The low threshold works the same way in the opposite
+/// direction. A zone change is only reported after the value has been in
+/// the new zone for `persistence` consecutive readings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Window {
+    /// Monitored quantity
+    pub quantity: Quantity,
+    /// Low threshold
+    pub low: f32,
+    /// High threshold
+    pub high: f32,
+    /// Hysteresis
+    pub hysteresis: f32,
+    /// Number of consecutive readings necessary to report a zone change
+    pub persistence: u16,
+}
+
+impl Window {
+    /// Create a new window without hysteresis and with a persistence of 1.
+    pub const fn new(quantity: Quantity, low: f32, high: f32) -> Self {
+        Window {
+            quantity,
+            low,
+            high,
+            hysteresis: 0.0,
+            persistence: 1,
+        }
+    }
+
+    /// Set the hysteresis.
+    pub const fn with_hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Set the persistence. A value of 0 is treated as 1.
+    pub const fn with_persistence(mut self, persistence: u16) -> Self {
+        self.persistence = persistence;
+        self
+    }
+
+    /// Zone of a value given the current zone
+    fn zone(&self, current: Zone, value: f32) -> Zone {
+        let high = match current {
+            Zone::Above => self.high - self.hysteresis,
+            _ => self.high,
+        };
+        let low = match current {
+            Zone::Below => self.low + self.hysteresis,
+            _ => self.low,
+        };
+        if value > high {
+            Zone::Above
+        } else if value < low {
+            Zone::Below
+        } else {
+            Zone::Inside
+        }
+    }
+}
+
+/// Zone change of a window
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Event {
+    /// Index of the window
+    pub window: usize,
+    /// Previous zone
+    pub from: Zone,
+    /// New zone
+    pub to: Zone,
+    /// Value of the quantity at the reading that completed the persistence
+    pub value: f32,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct WindowState {
+    zone: Zone,
+    candidate: Zone,
+    count: u16,
+}
+
+/// Event detector evaluating `N` windows
+///
+/// All windows start in the `Inside` zone.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EventDetector<const N: usize> {
+    windows: [Window; N],
+    states: [WindowState; N],
+}
+
+impl<const N: usize> EventDetector<N> {
+    /// Create a new event detector.
+    pub fn new(windows: [Window; N]) -> Self {
+        EventDetector {
+            windows,
+            states: [WindowState::default(); N],
+        }
+    }
+
+    /// Windows
+    pub fn windows(&self) -> &[Window; N] {
+        &self.windows
+    }
+
+    /// Current zone of each window
+    pub fn zones(&self) -> [Zone; N] {
+        let mut zones = [Zone::Inside; N];
+        for (zone, state) in zones.iter_mut().zip(self.states.iter()) {
+            *zone = state.zone;
+        }
+        zones
+    }
+
+    /// Return all windows to the `Inside` zone.
+    pub fn reset(&mut self) {
+        self.states = [WindowState::default(); N];
+    }
+
+    /// Evaluate a measurement taken at the given RGB data sensing range.
+    ///
+    /// Returns the event of each window, if any.
+    pub fn process(&mut self, measurement: &Measurement, range: Range) -> [Option<Event>; N] {
+        let mut events = [None; N];
+        let windows = self.windows.iter().zip(self.states.iter_mut());
+        for (index, (window, state)) in windows.enumerate() {
+            let value = match window.quantity.value(measurement, range) {
+                Some(value) => value,
+                None => continue,
+            };
+            let zone = window.zone(state.zone, value);
+            if zone == state.zone {
+                state.count = 0;
+                continue;
+            }
+            if zone == state.candidate && state.count > 0 {
+                state.count = state.count.saturating_add(1);
+            } else {
+                state.candidate = zone;
+                state.count = 1;
+            }
+            if state.count >= window.persistence {
+                events[index] = Some(Event {
+                    window: index,
+                    from: state.zone,
+                    to: zone,
+                    value,
+                });
+                state.zone = zone;
+                state.count = 0;
+            }
+        }
+        events
+    }
+
+    /// Read the colors from the sensor and evaluate them.
+    pub fn poll<I2C, E>(
+        &mut self,
+        sensor: &mut Isl29125<I2C>,
+    ) -> Result<[Option<Event>; N], Error<E>>
+    where
+        I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    {
+        let measurement = sensor.read()?;
+        Ok(self.process(&measurement, sensor.range()))
+    }
+
+    /// Read and evaluate the colors only if the hardware interrupt triggered.
+    ///
+    /// If an interrupt threshold assignment is configured in the sensor,
+    /// the status is read first (which clears the interrupt) and the colors
+    /// are only read and evaluated if the interrupt had triggered. Otherwise
+    /// no events are returned. This allows using the hardware window as a
+    /// wake-up hint, for example configuring it around the current light
+    /// level and polling only after the INT pin signals a change.
+    ///
+    /// Without an interrupt threshold assignment this is the same as
+    /// [`poll()`](#method.poll).
+    pub fn poll_if_triggered<I2C, E>(
+        &mut self,
+        sensor: &mut Isl29125<I2C>,
+    ) -> Result<[Option<Event>; N], Error<E>>
+    where
+        I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    {
+        if sensor.interrupt_threshold_assignment() != InterruptThresholdAssignment::None
+            && !sensor.status()?.interrupt_triggered
+        {
+            return Ok([None; N]);
+        }
+        self.poll(sensor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(red: u16, green: u16, blue: u16) -> Measurement {
+        Measurement::new(red, green, blue, Resolution::Bit16)
+    }
+
+    fn green_window() -> Window {
+        Window::new(Quantity::Channel(Channel::Green), 1000.0, 2000.0)
+    }
+
+    fn zones<const N: usize>(events: [Option<Event>; N]) -> [Option<Zone>; N] {
+        let mut zones = [None; N];
+        for (zone, event) in zones.iter_mut().zip(events.iter()) {
+            *zone = event.map(|e| e.to);
+        }
+        zones
+    }
+
+    #[test]
+    fn reports_zone_changes() {
+        let mut detector = EventDetector::new([green_window()]);
+        assert_eq!(
+            [None],
+            zones(detector.process(&m(0, 1500, 0), Range::Lux375))
+        );
+        let events = detector.process(&m(0, 2500, 0), Range::Lux375);
+        let expected = Event {
+            window: 0,
+            from: Zone::Inside,
+            to: Zone::Above,
+            value: 2500.0,
+        };
+        assert_eq!([Some(expected)], events);
+        assert_eq!(
+            [None],
+            zones(detector.process(&m(0, 2600, 0), Range::Lux375))
+        );
+        let events = detector.process(&m(0, 500, 0), Range::Lux375);
+        assert_eq!([Some(Zone::Below)], zones(events));
+        assert_eq!(Zone::Above, events[0].unwrap().from);
+        assert_eq!([Zone::Below], detector.zones());
+    }
+
+    #[test]
+    fn applies_hysteresis() {
+        let mut detector = EventDetector::new([green_window().with_hysteresis(100.0)]);
+        detector.process(&m(0, 2500, 0), Range::Lux375);
+        assert_eq!(
+            [None],
+            zones(detector.process(&m(0, 1950, 0), Range::Lux375))
+        );
+        let events = detector.process(&m(0, 1899, 0), Range::Lux375);
+        assert_eq!([Some(Zone::Inside)], zones(events));
+        detector.process(&m(0, 900, 0), Range::Lux375);
+        assert_eq!(
+            [None],
+            zones(detector.process(&m(0, 1050, 0), Range::Lux375))
+        );
+        let events = detector.process(&m(0, 1101, 0), Range::Lux375);
+        assert_eq!([Some(Zone::Inside)], zones(events));
+    }
+
+    #[test]
+    fn applies_persistence() {
+        let mut detector = EventDetector::new([green_window().with_persistence(3)]);
+        detector.process(&m(0, 2500, 0), Range::Lux375);
+        detector.process(&m(0, 2500, 0), Range::Lux375);
+        // An interruption restarts the count.
+        detector.process(&m(0, 1500, 0), Range::Lux375);
+        detector.process(&m(0, 2500, 0), Range::Lux375);
+        detector.process(&m(0, 2500, 0), Range::Lux375);
+        assert_eq!([Zone::Inside], detector.zones());
+        let events = detector.process(&m(0, 2500, 0), Range::Lux375);
+        assert_eq!([Some(Zone::Above)], zones(events));
+    }
+
+    #[test]
+    fn evaluates_windows_independently() {
+        let mut detector = EventDetector::new([
+            Window::new(Quantity::Channel(Channel::Red), 0.0, 50000.0),
+            Window::new(Quantity::Ratio(Channel::Red, Channel::Blue), 3.0, 5.0),
+            Window::new(Quantity::Lux, 10.0, 100.0),
+            Window::new(Quantity::Cct, 8000.0, 20000.0),
+        ]);
+        let events = detector.process(&m(40000, 30000, 15000), Range::Lux375);
+        let expected = [
+            None,
+            Some(Zone::Below),
+            Some(Zone::Above),
+            Some(Zone::Below),
+        ];
+        assert_eq!(expected, zones(events));
+        // Ratio and CCT cannot be computed and keep their zones.
+        let events = detector.process(&m(0, 0, 0), Range::Lux375);
+        assert_eq!([None, None, Some(Zone::Below), None], zones(events));
+        assert_eq!(
+            [Zone::Inside, Zone::Below, Zone::Below, Zone::Below],
+            detector.zones()
+        );
+        detector.reset();
+        assert_eq!([Zone::Inside; 4], detector.zones());
+    }
+
+    #[test]
+    fn channel_thresholds_do_not_depend_on_resolution() {
+        let mut detector = EventDetector::new([green_window()]);
+        let twelve_bit = Measurement::new(0, 250, 0, Resolution::Bit12);
+        let events = detector.process(&twelve_bit, Range::Lux375);
+        assert_eq!([Some(Zone::Above)], zones(events));
+    }
+}
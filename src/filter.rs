@@ -0,0 +1,360 @@
+//! Digital filters for measurement streams.
+//!
+//! All filters work on each color channel independently and use only integer
+//! arithmetic so that they can run on microcontrollers without an FPU.
+//! The output has the resolution of the last input measurement, so if the
+//! resolution changes, measurements should be rescaled with
+//! [`Measurement::to_resolution()`] before being filtered or the filter should
+//! be reset.
+//!
+//! Filters can be combined with [`Filter::then()`]:
+//!
+//! ```
+//! use isl29125::filter::{ExponentialSmoothing, Filter, Median};
+//! use isl29125::{Measurement, Resolution};
+//!
+//! let mut filter = Median::<3>::new().then(ExponentialSmoothing::new(64));
+//! let m = Measurement::new(100, 200, 300, Resolution::Bit16);
+//! let filtered = filter.update(m);
+//! assert_eq!(m, filtered);
+//! ```
+//!
+//! [`Filter::then()`]: trait.Filter.html#method.then
+//! [`Measurement::to_resolution()`]: ../struct.Measurement.html#method.to_resolution
+
+use crate::{Measurement, Resolution};
+
+/// Filter operating on a stream of measurements
+pub trait Filter {
+    /// Process a new measurement and return the filtered value.
+    fn update(&mut self, measurement: Measurement) -> Measurement;
+
+    /// Forget all the past measurements.
+    fn reset(&mut self);
+
+    /// Feed the output of this filter into `next`.
+    fn then<F: Filter>(self, next: F) -> Chain<Self, F>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+/// Two filters applied one after the other
+///
+/// Created with [`Filter::then()`](trait.Filter.html#method.then).
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: Filter, B: Filter> Filter for Chain<A, B> {
+    fn update(&mut self, measurement: Measurement) -> Measurement {
+        let intermediate = self.first.update(measurement);
+        self.second.update(intermediate)
+    }
+
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+    }
+}
+
+/// Moving average over the last `N` measurements
+///
+/// Until `N` measurements have been received, the average of the ones
+/// received so far is returned.
+#[derive(Debug, Clone)]
+pub struct MovingAverage<const N: usize> {
+    window: [[u16; 3]; N],
+    next: usize,
+    len: usize,
+    sums: [u32; 3],
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Create a new moving average filter.
+    pub const fn new() -> Self {
+        MovingAverage {
+            window: [[0; 3]; N],
+            next: 0,
+            len: 0,
+            sums: [0; 3],
+        }
+    }
+}
+
+impl<const N: usize> Filter for MovingAverage<N> {
+    fn update(&mut self, measurement: Measurement) -> Measurement {
+        if N == 0 {
+            return measurement;
+        }
+        let values = channels(&measurement);
+        for (i, value) in values.iter().enumerate() {
+            if self.len == N {
+                self.sums[i] -= u32::from(self.window[self.next][i]);
+            }
+            self.sums[i] += u32::from(*value);
+        }
+        self.window[self.next] = values;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        let len = self.len as u32;
+        from_channels(measurement.resolution, |i| {
+            ((self.sums[i] + len / 2) / len) as u16
+        })
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// Exponential smoothing
+///
+/// Each output is `y = y_prev + alpha * (x - y_prev)` where `alpha` is given
+/// in 1/256 units, so that `256` disables the smoothing and lower values
+/// smooth more. The state is kept with 8 fractional bits to avoid the output
+/// getting stuck away from a constant input due to rounding.
+#[derive(Debug, Clone)]
+pub struct ExponentialSmoothing {
+    alpha: u16,
+    state: Option<[u32; 3]>,
+}
+
+impl ExponentialSmoothing {
+    /// Create a new exponential smoothing filter.
+    ///
+    /// `alpha` is given in 1/256 units and will be clamped to `[1-256]`.
+    pub fn new(alpha: u16) -> Self {
+        ExponentialSmoothing {
+            alpha: alpha.clamp(1, 256),
+            state: None,
+        }
+    }
+}
+
+impl Filter for ExponentialSmoothing {
+    fn update(&mut self, measurement: Measurement) -> Measurement {
+        let values = channels(&measurement);
+        let state = match self.state {
+            None => from_array(|i| u32::from(values[i]) << 8),
+            Some(state) => from_array(|i| {
+                let input = i64::from(values[i]) << 8;
+                let previous = i64::from(state[i]);
+                (previous + (i64::from(self.alpha) * (input - previous)) / 256) as u32
+            }),
+        };
+        self.state = Some(state);
+        from_channels(measurement.resolution, |i| ((state[i] + 0x80) >> 8) as u16)
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+/// Median of the last `N` measurements
+///
+/// Good at removing isolated spikes. Until `N` measurements have been
+/// received, the median of the ones received so far is returned. For an
+/// even number of values the lower median is returned.
+#[derive(Debug, Clone)]
+pub struct Median<const N: usize> {
+    window: [[u16; 3]; N],
+    next: usize,
+    len: usize,
+}
+
+impl<const N: usize> Default for Median<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Median<N> {
+    /// Create a new median filter.
+    pub const fn new() -> Self {
+        Median {
+            window: [[0; 3]; N],
+            next: 0,
+            len: 0,
+        }
+    }
+}
+
+impl<const N: usize> Filter for Median<N> {
+    fn update(&mut self, measurement: Measurement) -> Measurement {
+        if N == 0 {
+            return measurement;
+        }
+        self.window[self.next] = channels(&measurement);
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        from_channels(measurement.resolution, |i| {
+            let mut values = [0; N];
+            for (value, stored) in values.iter_mut().zip(self.window[..self.len].iter()) {
+                *value = stored[i];
+            }
+            let values = &mut values[..self.len];
+            values.sort_unstable();
+            values[(self.len - 1) / 2]
+        })
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+}
+
+/// One-dimensional Kalman filter for each channel
+///
+/// The light level is modeled as a constant affected by random changes with
+/// variance `process_noise` between measurements. The measurements have a
+/// noise variance of `measurement_noise`. Both variances are given in
+/// counts². A higher ratio of measurement to process noise results in
+/// smoother but slower output.
+#[derive(Debug, Clone)]
+pub struct Kalman {
+    process_noise: u32,
+    measurement_noise: u32,
+    // Estimate with 8 fractional bits and its error variance in counts².
+    state: Option<[(u32, u32); 3]>,
+}
+
+impl Kalman {
+    /// Create a new Kalman filter.
+    ///
+    /// `measurement_noise` will be clamped to a minimum of 1.
+    pub fn new(process_noise: u32, measurement_noise: u32) -> Self {
+        Kalman {
+            process_noise,
+            measurement_noise: measurement_noise.max(1),
+            state: None,
+        }
+    }
+}
+
+impl Filter for Kalman {
+    fn update(&mut self, measurement: Measurement) -> Measurement {
+        let values = channels(&measurement);
+        let r = u64::from(self.measurement_noise);
+        let state = match self.state {
+            None => from_array(|i| (u32::from(values[i]) << 8, self.measurement_noise)),
+            Some(state) => from_array(|i| {
+                let (estimate, variance) = state[i];
+                let predicted_variance = u64::from(variance) + u64::from(self.process_noise);
+                // Kalman gain with 16 fractional bits
+                let gain = ((predicted_variance << 16) / (predicted_variance + r)) as i64;
+                let innovation = (i64::from(values[i]) << 8) - i64::from(estimate);
+                let estimate = (i64::from(estimate) + ((gain * innovation) >> 16)) as u32;
+                let variance = (predicted_variance * (0x1_0000 - gain as u64)) >> 16;
+                (estimate, variance.min(u64::from(u32::MAX)) as u32)
+            }),
+        };
+        self.state = Some(state);
+        from_channels(measurement.resolution, |i| {
+            ((state[i].0 + 0x80) >> 8) as u16
+        })
+    }
+
+    fn reset(&mut self) {
+        self.state = None;
+    }
+}
+
+fn channels(m: &Measurement) -> [u16; 3] {
+    [m.red, m.green, m.blue]
+}
+
+fn from_channels(resolution: Resolution, f: impl Fn(usize) -> u16) -> Measurement {
+    Measurement::new(f(0), f(1), f(2), resolution)
+}
+
+fn from_array<T>(f: impl Fn(usize) -> T) -> [T; 3] {
+    [f(0), f(1), f(2)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(red: u16, green: u16, blue: u16) -> Measurement {
+        Measurement::new(red, green, blue, Resolution::Bit16)
+    }
+
+    fn feed<F: Filter>(filter: &mut F, values: &[u16]) -> Measurement {
+        let mut output = m(0, 0, 0);
+        for v in values {
+            output = filter.update(m(*v, *v, 65535 - *v));
+        }
+        output
+    }
+
+    #[test]
+    fn moving_average_averages_window() {
+        let mut filter = MovingAverage::<4>::new();
+        assert_eq!(m(10, 10, 65525), feed(&mut filter, &[10]));
+        assert_eq!(m(15, 15, 65520), feed(&mut filter, &[20]));
+        assert_eq!(m(65, 65, 65470), feed(&mut filter, &[30, 40, 100, 90]));
+    }
+
+    #[test]
+    fn exponential_smoothing_converges() {
+        let mut filter = ExponentialSmoothing::new(128);
+        assert_eq!(m(0, 0, 65535), feed(&mut filter, &[0]));
+        assert_eq!(m(50, 50, 65485), feed(&mut filter, &[100]));
+        assert_eq!(m(75, 75, 65460), feed(&mut filter, &[100]));
+        assert_eq!(m(100, 100, 65435), feed(&mut filter, &[100; 20]));
+    }
+
+    #[test]
+    fn exponential_smoothing_without_smoothing_passes_through() {
+        let mut filter = ExponentialSmoothing::new(256);
+        assert_eq!(m(7, 7, 65528), feed(&mut filter, &[1000, 3, 7]));
+    }
+
+    #[test]
+    fn median_removes_spikes() {
+        let mut filter = Median::<3>::new();
+        assert_eq!(m(11, 11, 65524), feed(&mut filter, &[10, 60000, 11]));
+        assert_eq!(m(12, 12, 65523), feed(&mut filter, &[12]));
+    }
+
+    #[test]
+    fn median_of_partial_window() {
+        let mut filter = Median::<5>::new();
+        assert_eq!(m(5, 5, 65530), feed(&mut filter, &[5]));
+        assert_eq!(m(3, 3, 65530), feed(&mut filter, &[3]));
+        assert_eq!(m(4, 4, 65531), feed(&mut filter, &[4]));
+    }
+
+    #[test]
+    fn kalman_converges_to_constant_input() {
+        let mut filter = Kalman::new(1, 100);
+        let first = feed(&mut filter, &[1000]);
+        assert_eq!(m(1000, 1000, 64535), first);
+        let step = feed(&mut filter, &[2000]);
+        assert!(step.red > 1000 && step.red < 2000);
+        assert_eq!(m(2000, 2000, 63535), feed(&mut filter, &[2000; 200]));
+    }
+
+    #[test]
+    fn can_chain_and_reset() {
+        let mut filter = MovingAverage::<2>::new().then(Median::<3>::new());
+        feed(&mut filter, &[100, 200, 300]);
+        filter.reset();
+        assert_eq!(m(40, 40, 65495), feed(&mut filter, &[40]));
+    }
+}
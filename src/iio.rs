@@ -0,0 +1,485 @@
+//! Emulation of the Linux IIO sysfs interface.
+//!
+//! Requires the `std` feature.
+//!
+//! An [`IioDevice`] maintains a directory of attribute files like the ones
+//! the kernel creates under `/sys/bus/iio/devices/iio:deviceX`, so that tools
+//! written for the IIO interface can be used without a kernel driver. Call
+//! [`update()`] periodically: it applies the values written to the writable
+//! attributes, takes a new measurement and refreshes the files.
+//!
+//! | Attribute                                  | Access | Meaning                                    |
+//! |--------------------------------------------|--------|--------------------------------------------|
+//! | `name`                                     | R      | `isl29125`                                 |
+//! | `in_intensity_red_raw` (`green`, `blue`)   | R      | Color counts                               |
+//! | `in_illuminance_input`                     | R      | Approximate illuminance in lux             |
+//! | `in_intensity_scale`                       | RW     | Lux per count. Sets range and resolution   |
+//! | `in_intensity_scale_available`             | R      |                                            |
+//! | `in_intensity_integration_time`            | RW     | Seconds. Selects the resolution            |
+//! | `in_intensity_integration_time_available`  | R      |                                            |
+//! | `in_intensity_ir_compensation`             | RW     | IR adjust, `0`-`63` or `106`-`169`         |
+//! | `operating_mode`                           | RW     | Enabled channels, e.g. `red_green_blue`    |
+//! | `operating_mode_available`                 | R      |                                            |
+//! | `in_intensity_thresh_falling_value`        | RW     | Low interrupt threshold in counts          |
+//! | `in_intensity_thresh_rising_value`         | RW     | High interrupt threshold in counts         |
+//! | `in_intensity_thresh_channel`              | RW     | Channel compared against the thresholds    |
+//! | `in_intensity_thresh_channel_available`    | R      |                                            |
+//! | `in_intensity_thresh_period`               | RW     | Consecutive faults to trigger an interrupt |
+//! | `in_intensity_thresh_period_available`     | R      |                                            |
+//! | `interrupt_pin_mode`                       | RW     | INT pin function, `interrupt`/`sync_start` |
+//! | `interrupt_pin_mode_available`             | R      |                                            |
+//! | `conversion_done_interrupt_en`             | RW     | `1` to interrupt when a conversion is done |
+//!
+//! Files are replaced atomically. Invalid values written to an attribute are
+//! reverted and reported as `Error::InvalidInputData`.
+//!
+//! The status flags are not exposed, since reading them clears the INT
+//! output and the interrupt flag, which would interfere with applications
+//! handling the interrupts. Use [`sensor()`] to read them instead.
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{iio::IioDevice, Isl29125, OperatingMode};
+//! use std::{thread, time::Duration};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let mut iio = IioDevice::new(sensor, "/run/isl29125/iio:device0").unwrap();
+//! loop {
+//!     if let Err(e) = iio.update() {
+//!         eprintln!("{:?}", e);
+//!     }
+//!     thread::sleep(Duration::from_millis(100));
+//! }
+//! # }
+//! ```
+//!
+//! [`IioDevice`]: struct.IioDevice.html
+//! [`update()`]: struct.IioDevice.html#method.update
+//! [`sensor()`]: struct.IioDevice.html#method.sensor
+
+use crate::{
+    Error, FaultCount, IRFilteringRange, InterruptPinMode, InterruptThresholdAssignment, Isl29125,
+    Measurement, OperatingMode, Range, Resolution,
+};
+use embedded_hal::blocking::i2c;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// IIO device error
+#[derive(Debug)]
+pub enum IioError<E> {
+    /// Device error or invalid attribute value
+    Device(Error<E>),
+    /// File I/O error
+    Io(io::Error),
+}
+
+impl<E> From<Error<E>> for IioError<E> {
+    fn from(e: Error<E>) -> Self {
+        IioError::Device(e)
+    }
+}
+
+impl<E> From<io::Error> for IioError<E> {
+    fn from(e: io::Error) -> Self {
+        IioError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Attribute {
+    Name,
+    RedRaw,
+    GreenRaw,
+    BlueRaw,
+    Illuminance,
+    Scale,
+    ScaleAvailable,
+    IntegrationTime,
+    IntegrationTimeAvailable,
+    IrCompensation,
+    Mode,
+    ModeAvailable,
+    ThreshFalling,
+    ThreshRising,
+    ThreshChannel,
+    ThreshChannelAvailable,
+    ThreshPeriod,
+    ThreshPeriodAvailable,
+    PinMode,
+    PinModeAvailable,
+    ConversionDoneEnable,
+}
+
+const ATTRIBUTES: [Attribute; 21] = [
+    Attribute::Name,
+    Attribute::RedRaw,
+    Attribute::GreenRaw,
+    Attribute::BlueRaw,
+    Attribute::Illuminance,
+    Attribute::Scale,
+    Attribute::ScaleAvailable,
+    Attribute::IntegrationTime,
+    Attribute::IntegrationTimeAvailable,
+    Attribute::IrCompensation,
+    Attribute::Mode,
+    Attribute::ModeAvailable,
+    Attribute::ThreshFalling,
+    Attribute::ThreshRising,
+    Attribute::ThreshChannel,
+    Attribute::ThreshChannelAvailable,
+    Attribute::ThreshPeriod,
+    Attribute::ThreshPeriodAvailable,
+    Attribute::PinMode,
+    Attribute::PinModeAvailable,
+    Attribute::ConversionDoneEnable,
+];
+
+impl Attribute {
+    fn file_name(self) -> &'static str {
+        match self {
+            Attribute::Name => "name",
+            Attribute::RedRaw => "in_intensity_red_raw",
+            Attribute::GreenRaw => "in_intensity_green_raw",
+            Attribute::BlueRaw => "in_intensity_blue_raw",
+            Attribute::Illuminance => "in_illuminance_input",
+            Attribute::Scale => "in_intensity_scale",
+            Attribute::ScaleAvailable => "in_intensity_scale_available",
+            Attribute::IntegrationTime => "in_intensity_integration_time",
+            Attribute::IntegrationTimeAvailable => "in_intensity_integration_time_available",
+            Attribute::IrCompensation => "in_intensity_ir_compensation",
+            Attribute::Mode => "operating_mode",
+            Attribute::ModeAvailable => "operating_mode_available",
+            Attribute::ThreshFalling => "in_intensity_thresh_falling_value",
+            Attribute::ThreshRising => "in_intensity_thresh_rising_value",
+            Attribute::ThreshChannel => "in_intensity_thresh_channel",
+            Attribute::ThreshChannelAvailable => "in_intensity_thresh_channel_available",
+            Attribute::ThreshPeriod => "in_intensity_thresh_period",
+            Attribute::ThreshPeriodAvailable => "in_intensity_thresh_period_available",
+            Attribute::PinMode => "interrupt_pin_mode",
+            Attribute::PinModeAvailable => "interrupt_pin_mode_available",
+            Attribute::ConversionDoneEnable => "conversion_done_interrupt_en",
+        }
+    }
+
+    fn is_writable(self) -> bool {
+        matches!(
+            self,
+            Attribute::Scale
+                | Attribute::IntegrationTime
+                | Attribute::IrCompensation
+                | Attribute::Mode
+                | Attribute::ThreshFalling
+                | Attribute::ThreshRising
+                | Attribute::ThreshChannel
+                | Attribute::ThreshPeriod
+                | Attribute::PinMode
+                | Attribute::ConversionDoneEnable
+        )
+    }
+}
+
+const SCALES: [(Range, Resolution); 4] = [
+    (Range::Lux375, Resolution::Bit16),
+    (Range::Lux10000, Resolution::Bit16),
+    (Range::Lux375, Resolution::Bit12),
+    (Range::Lux10000, Resolution::Bit12),
+];
+
+const RESOLUTIONS: [Resolution; 2] = [Resolution::Bit16, Resolution::Bit12];
+
+const MODES: [(OperatingMode, &str); 8] = [
+    (OperatingMode::PowerDown, "power_down"),
+    (OperatingMode::StandBy, "standby"),
+    (OperatingMode::RedOnly, "red"),
+    (OperatingMode::GreenOnly, "green"),
+    (OperatingMode::BlueOnly, "blue"),
+    (OperatingMode::RedGreen, "red_green"),
+    (OperatingMode::GreenBlue, "green_blue"),
+    (OperatingMode::RedGreenBlue, "red_green_blue"),
+];
+
+const THRESHOLD_CHANNELS: [(InterruptThresholdAssignment, &str); 4] = [
+    (InterruptThresholdAssignment::None, "none"),
+    (InterruptThresholdAssignment::Green, "green"),
+    (InterruptThresholdAssignment::Red, "red"),
+    (InterruptThresholdAssignment::Blue, "blue"),
+];
+
+const PERIODS: [(FaultCount, &str); 4] = [
+    (FaultCount::One, "1"),
+    (FaultCount::Two, "2"),
+    (FaultCount::Four, "4"),
+    (FaultCount::Eight, "8"),
+];
+
+const PIN_MODES: [(InterruptPinMode, &str); 2] = [
+    (InterruptPinMode::Interrupt, "interrupt"),
+    (InterruptPinMode::SyncStart, "sync_start"),
+];
+
+fn scale(range: Range, resolution: Resolution) -> String {
+    format!(
+        "{:.6}",
+        range.full_scale_lux() as f64 / f64::from(resolution.max_count())
+    )
+}
+
+fn integration_time(resolution: Resolution) -> String {
+    format!("{:.6}", f64::from(resolution.conversion_time_us()) / 1e6)
+}
+
+fn names<T>(table: &[(T, &str)]) -> String {
+    let names: Vec<&str> = table.iter().map(|(_, name)| *name).collect();
+    names.join(" ")
+}
+
+fn lookup<T: Copy>(table: &[(T, &str)], value: &str) -> Option<T> {
+    table
+        .iter()
+        .find(|(_, name)| *name == value)
+        .map(|(t, _)| *t)
+}
+
+fn name_of<T: PartialEq>(table: &[(T, &'static str)], value: T) -> &'static str {
+    table
+        .iter()
+        .find(|(t, _)| *t == value)
+        .map_or("", |(_, name)| *name)
+}
+
+/// Whether the written number matches the formatted one
+fn same_number(written: &str, formatted: &str) -> bool {
+    match (written.parse::<f64>(), formatted.parse::<f64>()) {
+        (Ok(a), Ok(b)) => (a - b).abs() <= b * 1e-3,
+        _ => false,
+    }
+}
+
+/// IIO-style sysfs attribute directory
+#[derive(Debug)]
+pub struct IioDevice<I2C> {
+    sensor: Isl29125<I2C>,
+    directory: PathBuf,
+    measurement: Measurement,
+    contents: [String; ATTRIBUTES.len()],
+}
+
+impl<I2C, E> IioDevice<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    /// Create the attribute directory if needed and write all attributes.
+    ///
+    /// The color counts read zero until the first [`update()`](#method.update).
+    pub fn new<P: Into<PathBuf>>(sensor: Isl29125<I2C>, directory: P) -> io::Result<Self> {
+        let mut device = IioDevice {
+            sensor,
+            directory: directory.into(),
+            measurement: Measurement::default(),
+            contents: Default::default(),
+        };
+        fs::create_dir_all(&device.directory)?;
+        device.refresh(true)?;
+        Ok(device)
+    }
+
+    /// Destroy the IIO device and return the driver.
+    ///
+    /// The attribute files are left in place.
+    pub fn destroy(self) -> Isl29125<I2C> {
+        self.sensor
+    }
+
+    /// Access the driver, for example to change its configuration.
+    pub fn sensor(&mut self) -> &mut Isl29125<I2C> {
+        &mut self.sensor
+    }
+
+    /// Attribute directory
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn value(&self, attribute: Attribute) -> String {
+        let s = &self.sensor;
+        let m = &self.measurement;
+        match attribute {
+            Attribute::Name => "isl29125".into(),
+            Attribute::RedRaw => m.red.to_string(),
+            Attribute::GreenRaw => m.green.to_string(),
+            Attribute::BlueRaw => m.blue.to_string(),
+            Attribute::Illuminance => format!("{:.3}", m.lux(s.range())),
+            Attribute::Scale => scale(s.range(), s.resolution()),
+            Attribute::ScaleAvailable => {
+                let scales: Vec<String> = SCALES.iter().map(|(r, res)| scale(*r, *res)).collect();
+                scales.join(" ")
+            }
+            Attribute::IntegrationTime => integration_time(s.resolution()),
+            Attribute::IntegrationTimeAvailable => {
+                let times: Vec<String> = RESOLUTIONS.iter().map(|r| integration_time(*r)).collect();
+                times.join(" ")
+            }
+            Attribute::IrCompensation => match s.ir_filtering() {
+                IRFilteringRange::Lower(value) => value.to_string(),
+                IRFilteringRange::Higher(value) => (106 + u16::from(value)).to_string(),
+            },
+            Attribute::Mode => name_of(&MODES, s.operating_mode()).into(),
+            Attribute::ModeAvailable => names(&MODES),
+            Attribute::ThreshFalling => s.interrupt_thresholds().0.to_string(),
+            Attribute::ThreshRising => s.interrupt_thresholds().1.to_string(),
+            Attribute::ThreshChannel => {
+                name_of(&THRESHOLD_CHANNELS, s.interrupt_threshold_assignment()).into()
+            }
+            Attribute::ThreshChannelAvailable => names(&THRESHOLD_CHANNELS),
+            Attribute::ThreshPeriod => name_of(&PERIODS, s.fault_count()).into(),
+            Attribute::ThreshPeriodAvailable => names(&PERIODS),
+            Attribute::PinMode => name_of(&PIN_MODES, s.interrupt_pin_mode()).into(),
+            Attribute::PinModeAvailable => names(&PIN_MODES),
+            Attribute::ConversionDoneEnable => {
+                u8::from(s.interrupt_on_conversion_done()).to_string()
+            }
+        }
+    }
+
+    fn path(&self, attribute: Attribute) -> PathBuf {
+        self.directory.join(attribute.file_name())
+    }
+
+    /// Rewrite the attribute files whose value changed, or all of them.
+    fn refresh(&mut self, all: bool) -> io::Result<()> {
+        for (i, attribute) in ATTRIBUTES.iter().enumerate() {
+            let mut value = self.value(*attribute);
+            value.push('\n');
+            if all || value != self.contents[i] {
+                let path = self.path(*attribute);
+                let mut tmp = path.as_os_str().to_owned();
+                tmp.push(".tmp");
+                fs::write(&tmp, &value)?;
+                fs::rename(&tmp, &path)?;
+                self.contents[i] = value;
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the values written to the attributes, take a measurement and
+    /// refresh the attribute files.
+    ///
+    /// All written attributes are processed and the files are refreshed
+    /// even if some value is invalid. The first error is returned.
+    pub fn update(&mut self) -> Result<(), IioError<E>> {
+        let mut result = Ok(());
+        for (i, attribute) in ATTRIBUTES.iter().enumerate() {
+            if !attribute.is_writable() {
+                continue;
+            }
+            let written = match fs::read_to_string(self.path(*attribute)) {
+                Ok(written) => written,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                    self.contents[i].clear();
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+            if written.trim() != self.contents[i].trim() {
+                let applied = self.apply(*attribute, written.trim());
+                if applied.is_err() {
+                    // Force the previous value to be written back.
+                    self.contents[i].clear();
+                }
+                if result.is_ok() {
+                    result = applied;
+                }
+            }
+        }
+        match self.sensor.read() {
+            Ok(measurement) => self.measurement = measurement,
+            Err(e) => {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        self.refresh(false)?;
+        result.map_err(IioError::Device)
+    }
+
+    fn apply(&mut self, attribute: Attribute, value: &str) -> Result<(), Error<E>> {
+        let s = &mut self.sensor;
+        match attribute {
+            Attribute::Scale => {
+                let (range, resolution) = SCALES
+                    .iter()
+                    .find(|(r, res)| same_number(value, &scale(*r, *res)))
+                    .ok_or(Error::InvalidInputData)?;
+                let previous = s.range();
+                s.set_range(*range)?;
+                if let Err(e) = s.set_resolution(*resolution) {
+                    // Keep the range matching the reverted scale attribute.
+                    s.set_range(previous).ok();
+                    return Err(e);
+                }
+                Ok(())
+            }
+            Attribute::IntegrationTime => {
+                let resolution = RESOLUTIONS
+                    .iter()
+                    .find(|r| same_number(value, &integration_time(**r)))
+                    .ok_or(Error::InvalidInputData)?;
+                s.set_resolution(*resolution)
+            }
+            Attribute::IrCompensation => {
+                let value: u8 = value.parse().map_err(|_| Error::InvalidInputData)?;
+                let range = match value {
+                    0..=63 => IRFilteringRange::Lower(value),
+                    106..=169 => IRFilteringRange::Higher(value - 106),
+                    _ => return Err(Error::InvalidInputData),
+                };
+                s.set_ir_filtering(range)
+            }
+            Attribute::Mode => {
+                let mode = lookup(&MODES, value).ok_or(Error::InvalidInputData)?;
+                s.set_operating_mode(mode)
+            }
+            Attribute::ThreshFalling | Attribute::ThreshRising => {
+                let value: u16 = value.parse().map_err(|_| Error::InvalidInputData)?;
+                if value > s.resolution().max_count() {
+                    return Err(Error::InvalidInputData);
+                }
+                let (low, high) = s.interrupt_thresholds();
+                if attribute == Attribute::ThreshFalling {
+                    s.set_interrupt_thresholds(value, high)
+                } else {
+                    s.set_interrupt_thresholds(low, value)
+                }
+            }
+            Attribute::ThreshChannel => {
+                let assignment =
+                    lookup(&THRESHOLD_CHANNELS, value).ok_or(Error::InvalidInputData)?;
+                s.set_interrupt_threshold_assignment(assignment)
+            }
+            Attribute::ThreshPeriod => {
+                let fault_count = lookup(&PERIODS, value).ok_or(Error::InvalidInputData)?;
+                s.set_fault_count(fault_count)
+            }
+            Attribute::PinMode => {
+                let mode = lookup(&PIN_MODES, value).ok_or(Error::InvalidInputData)?;
+                s.set_interrupt_pin_mode(mode)
+            }
+            Attribute::ConversionDoneEnable => match value {
+                "0" => s.disable_interrupt_on_conversion_done(),
+                "1" => s.enable_interrupt_on_conversion_done(),
+                _ => Err(Error::InvalidInputData),
+            },
+            _ => Err(Error::InvalidInputData),
+        }
+    }
+}
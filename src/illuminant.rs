@@ -0,0 +1,373 @@
+//! Light source type detection.
+//!
+//! The light source is estimated from the correlated color temperature (CCT)
+//! of a measurement and, optionally, from its IR content. The IR content is
+//! measured by comparing the counts with the minimum and the maximum IR
+//! filtering (see [`measure_ir_ratio()`]).
+//!
+//! The estimation is done by a [`Classifier`] with a configurable table of
+//! [`Rule`]s. The [`DEFAULT_RULES`] are indicative and should be calibrated
+//! for the specific setup, since the CCT computed from the sensor counts
+//! depends on the optics in front of the sensor.
+//!
+//! ```
+//! use isl29125::illuminant::{Classifier, LightFeatures, LightSource, DEFAULT_RULES};
+//! use isl29125::{Measurement, Resolution};
+//!
+//! let classifier = Classifier::new(DEFAULT_RULES);
+//! let m = Measurement::new(40000, 24000, 8000, Resolution::Bit16);
+//! let features = LightFeatures::from_measurement(&m).unwrap().with_ir_ratio(0.4);
+//! let estimate = classifier.classify(&features).unwrap();
+//! assert_eq!(LightSource::Incandescent, estimate.source);
+//! ```
+//!
+//! [`measure_ir_ratio()`]: ../struct.Isl29125.html#method.measure_ir_ratio
+//! [`Classifier`]: struct.Classifier.html
+//! [`Rule`]: struct.Rule.html
+//! [`DEFAULT_RULES`]: constant.DEFAULT_RULES.html
+
+use crate::color::Xyz;
+use crate::{Error, IRFilteringRange, InterruptPinMode, Isl29125, Measurement};
+use embedded_hal::blocking::{delay::DelayUs, i2c};
+
+/// Light source class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LightSource {
+    /// Daylight
+    Daylight,
+    /// Incandescent or halogen lamp
+    Incandescent,
+    /// Fluorescent lamp
+    Fluorescent,
+    /// Warm white LED
+    WarmLed,
+    /// Cool white LED
+    CoolLed,
+    /// Mixture of several light sources
+    Mixed,
+}
+
+/// Features of the light used for the classification
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LightFeatures {
+    /// Correlated color temperature in kelvin
+    pub cct: f32,
+    /// IR content in `[0.0, 1.0]` as returned by
+    /// [`measure_ir_ratio()`](../struct.Isl29125.html#method.measure_ir_ratio)
+    pub ir_ratio: Option<f32>,
+}
+
+impl LightFeatures {
+    /// Compute the features of a measurement.
+    ///
+    /// Returns `None` for black measurements.
+    pub fn from_measurement(measurement: &Measurement) -> Option<Self> {
+        Some(LightFeatures {
+            cct: Xyz::from_measurement(measurement).cct()?,
+            ir_ratio: None,
+        })
+    }
+
+    /// Add the IR content.
+    pub fn with_ir_ratio(self, ir_ratio: f32) -> Self {
+        LightFeatures {
+            ir_ratio: Some(ir_ratio),
+            ..self
+        }
+    }
+}
+
+/// Classification rule
+///
+/// Features within all ranges of a rule match it fully. Features outside
+/// the ranges match it less the further away they are. The CCT distance is
+/// evaluated in mireds (`10⁶ / CCT`) so that it is perceptually uniform.
+/// The IR range is ignored if the features do not contain the IR content.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rule {
+    /// Light source identified by this rule
+    pub source: LightSource,
+    /// Minimum correlated color temperature in kelvin
+    pub min_cct: f32,
+    /// Maximum correlated color temperature in kelvin
+    pub max_cct: f32,
+    /// Minimum IR content
+    pub min_ir_ratio: f32,
+    /// Maximum IR content
+    pub max_ir_ratio: f32,
+}
+
+impl Rule {
+    /// Create a new rule.
+    pub const fn new(
+        source: LightSource,
+        min_cct: f32,
+        max_cct: f32,
+        min_ir_ratio: f32,
+        max_ir_ratio: f32,
+    ) -> Self {
+        Rule {
+            source,
+            min_cct,
+            max_cct,
+            min_ir_ratio,
+            max_ir_ratio,
+        }
+    }
+
+    /// How well the features match this rule between `0.0` and `1.0`
+    pub fn score(&self, features: &LightFeatures) -> f32 {
+        /// Distance in mireds counting as one unit
+        const MIRED_SCALE: f32 = 50.0;
+        /// Distance in IR content counting as one unit
+        const IR_SCALE: f32 = 0.1;
+        let outside = |value: f32, min: f32, max: f32| {
+            if value < min {
+                min - value
+            } else if value > max {
+                value - max
+            } else {
+                0.0
+            }
+        };
+        let mired = |cct: f32| 1e6 / cct;
+        // Higher temperatures have lower mired values.
+        let mut distance = outside(
+            mired(features.cct),
+            mired(self.max_cct),
+            mired(self.min_cct),
+        ) / MIRED_SCALE;
+        if let Some(ir_ratio) = features.ir_ratio {
+            distance += outside(ir_ratio, self.min_ir_ratio, self.max_ir_ratio) / IR_SCALE;
+        }
+        1.0 / (1.0 + distance)
+    }
+}
+
+/// Default classification rules
+pub const DEFAULT_RULES: [Rule; 5] = [
+    Rule::new(LightSource::Incandescent, 2200.0, 3200.0, 0.25, 1.0),
+    Rule::new(LightSource::WarmLed, 2500.0, 3500.0, 0.0, 0.04),
+    Rule::new(LightSource::Fluorescent, 3500.0, 5000.0, 0.04, 0.12),
+    Rule::new(LightSource::CoolLed, 4000.0, 7000.0, 0.0, 0.04),
+    Rule::new(LightSource::Daylight, 5000.0, 8000.0, 0.12, 0.25),
+];
+
+/// Estimated light source
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Estimate {
+    /// Light source class
+    pub source: LightSource,
+    /// Confidence between `0.0` and `1.0`
+    pub confidence: f32,
+}
+
+/// Light source classifier with a table of `N` rules
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classifier<const N: usize> {
+    rules: [Rule; N],
+    mixed_margin: f32,
+}
+
+impl<const N: usize> Classifier<N> {
+    /// Create a new classifier.
+    ///
+    /// The mixed lighting margin is `0.2`.
+    pub const fn new(rules: [Rule; N]) -> Self {
+        Classifier {
+            rules,
+            mixed_margin: 0.2,
+        }
+    }
+
+    /// Set the mixed lighting margin.
+    ///
+    /// See [`classify()`](#method.classify).
+    pub const fn with_mixed_margin(mut self, margin: f32) -> Self {
+        self.mixed_margin = margin;
+        self
+    }
+
+    /// Rules
+    pub fn rules(&self) -> &[Rule; N] {
+        &self.rules
+    }
+
+    /// Estimate the light source.
+    ///
+    /// The rule with the highest score determines the light source. The
+    /// confidence is the difference between its score and the highest score
+    /// of a rule for a different light source, so it is low if the features
+    /// match several light sources.
+    ///
+    /// If the features contain the IR content and the two best light sources
+    /// both score above `0.4` and less than the mixed lighting margin apart,
+    /// `LightSource::Mixed` is returned with the score of the second one as
+    /// confidence. Without IR content, the CCT ranges of several light
+    /// sources overlap, so this is reported as low confidence instead.
+    ///
+    /// Returns `None` if there are no rules.
+    pub fn classify(&self, features: &LightFeatures) -> Option<Estimate> {
+        let mut best: Option<(LightSource, f32)> = None;
+        for rule in &self.rules {
+            let score = rule.score(features);
+            match best {
+                Some((_, best_score)) if score <= best_score => (),
+                _ => best = Some((rule.source, score)),
+            }
+        }
+        let (source, best_score) = best?;
+        let second_score = self
+            .rules
+            .iter()
+            .filter(|rule| rule.source != source)
+            .map(|rule| rule.score(features))
+            .fold(0.0, f32::max);
+        let mixed = features.ir_ratio.is_some()
+            && second_score > 0.4
+            && best_score - second_score < self.mixed_margin;
+        Some(if mixed {
+            Estimate {
+                source: LightSource::Mixed,
+                confidence: second_score,
+            }
+        } else {
+            Estimate {
+                source,
+                confidence: best_score - second_score,
+            }
+        })
+    }
+}
+
+impl<I2C, E> Isl29125<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    /// Measure the IR content of the light
+    ///
+    /// The colors are measured with the minimum (`IRFilteringRange::Lower(0)`)
+    /// and the maximum (`IRFilteringRange::Higher(63)`) IR filtering. For
+    /// each of them the ADC conversion is restarted and a complete
+    /// conversion cycle is waited. The returned value is the fraction of the
+    /// counts removed by the maximum IR filtering, between `0.0` and `1.0`.
+    ///
+    /// The previous IR filtering is set again afterwards, even if reading
+    /// the data fails. In that case the read error is returned. If no color
+    /// channel is enabled in the current operating mode, the interrupt pin is
+    /// in `InterruptPinMode::SyncStart` or no light is detected with the
+    /// minimum IR filtering, `Error::InvalidInputData` is returned.
+    pub fn measure_ir_ratio<D>(&mut self, delay: &mut D) -> Result<f32, Error<E>>
+    where
+        D: DelayUs<u32>,
+    {
+        let cycle_time = self.operating_mode().cycle_time_us(self.resolution());
+        if cycle_time == 0 || self.interrupt_pin_mode() == InterruptPinMode::SyncStart {
+            return Err(Error::InvalidInputData);
+        }
+        let previous = self.ir_filtering();
+        let result = self.measure_ir_sums(delay, cycle_time);
+        let restored = self.set_ir_filtering(previous);
+        let (unfiltered, filtered) = result?;
+        restored?;
+        if unfiltered == 0 {
+            return Err(Error::InvalidInputData);
+        }
+        let ratio = 1.0 - filtered as f32 / unfiltered as f32;
+        Ok(ratio.clamp(0.0, 1.0))
+    }
+
+    fn measure_ir_sums<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        cycle_time: u32,
+    ) -> Result<(u32, u32), Error<E>> {
+        let mut sums = [0; 2];
+        let settings = [IRFilteringRange::Lower(0), IRFilteringRange::Higher(63)];
+        for (sum, setting) in sums.iter_mut().zip(settings.iter()) {
+            self.set_ir_filtering(*setting)?;
+            self.restart_conversion()?;
+            delay.delay_us(cycle_time);
+            let m = self.read()?;
+            *sum = u32::from(m.red) + u32::from(m.green) + u32::from(m.blue);
+        }
+        Ok((sums[0], sums[1]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn features(cct: f32, ir_ratio: Option<f32>) -> LightFeatures {
+        LightFeatures { cct, ir_ratio }
+    }
+
+    #[test]
+    fn score_decreases_outside_ranges() {
+        let rule = Rule::new(LightSource::WarmLed, 2500.0, 3500.0, 0.0, 0.04);
+        assert_eq!(1.0, rule.score(&features(3000.0, None)));
+        assert_eq!(1.0, rule.score(&features(3000.0, Some(0.02))));
+        let near = rule.score(&features(3700.0, None));
+        let far = rule.score(&features(6000.0, None));
+        assert!(near < 1.0 && far < near);
+        assert!(rule.score(&features(3000.0, Some(0.3))) < 0.5);
+    }
+
+    #[test]
+    fn can_classify_with_default_rules() {
+        let classifier = Classifier::new(DEFAULT_RULES);
+        let cases = [
+            (2700.0, 0.5, LightSource::Incandescent),
+            (2700.0, 0.01, LightSource::WarmLed),
+            (4200.0, 0.08, LightSource::Fluorescent),
+            (5500.0, 0.0, LightSource::CoolLed),
+            (6500.0, 0.2, LightSource::Daylight),
+        ];
+        for (cct, ir_ratio, source) in cases.iter() {
+            let estimate = classifier
+                .classify(&features(*cct, Some(*ir_ratio)))
+                .unwrap();
+            assert_eq!(*source, estimate.source, "{} K, IR: {}", cct, ir_ratio);
+            assert!(estimate.confidence > 0.25, "{:?}", estimate);
+        }
+    }
+
+    #[test]
+    fn ambiguous_without_ir_content() {
+        let classifier = Classifier::new(DEFAULT_RULES);
+        let estimate = classifier.classify(&features(2800.0, None)).unwrap();
+        assert_eq!(0.0, estimate.confidence);
+        assert_ne!(LightSource::Mixed, estimate.source);
+    }
+
+    #[test]
+    fn detects_mixed_lighting() {
+        let classifier = Classifier::new(DEFAULT_RULES);
+        // IR content between warm LED and incandescent
+        let estimate = classifier.classify(&features(2800.0, Some(0.145))).unwrap();
+        assert_eq!(LightSource::Mixed, estimate.source);
+        let classifier = classifier.with_mixed_margin(0.0);
+        let estimate = classifier.classify(&features(2800.0, Some(0.145))).unwrap();
+        assert_ne!(LightSource::Mixed, estimate.source);
+    }
+
+    #[test]
+    fn can_use_custom_rules() {
+        let classifier = Classifier::new([Rule::new(LightSource::Daylight, 0.0, 1e6, 0.0, 1.0)]);
+        let estimate = classifier.classify(&features(1000.0, Some(1.0))).unwrap();
+        assert_eq!(LightSource::Daylight, estimate.source);
+        assert_eq!(1.0, estimate.confidence);
+        assert_eq!(None, Classifier::new([]).classify(&features(1000.0, None)));
+    }
+
+    #[test]
+    fn black_has_no_features() {
+        let black = Measurement::new(0, 0, 0, crate::Resolution::Bit16);
+        assert_eq!(None, LightFeatures::from_measurement(&black));
+    }
+}
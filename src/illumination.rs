@@ -0,0 +1,196 @@
+//! Ambient-subtracted measurements with active illumination.
+//!
+//! For reflective color sensing, the target is lit by an illuminator (e.g. a
+//! white LED) controlled through an `OutputPin`. Measuring once with the
+//! illuminator off and once with it on and subtracting both readings removes
+//! the contribution of the ambient light.
+//!
+//! The illuminator is switched on and off through an `OutputPin` and the
+//! conversions are restarted through I²C. Dimming the illuminator with PWM
+//! and triggering the conversions in `InterruptPinMode::SyncStart` are not
+//! supported. A PWM channel with a fixed duty cycle can be used by wrapping
+//! it in a type implementing `OutputPin`.
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use hal::sysfs_gpio::Direction;
+//! use isl29125::{Isl29125, OperatingMode};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! let mut delay = hal::Delay;
+//! let mut led = hal::Pin::new(17);
+//! led.export().unwrap();
+//! led.set_direction(Direction::Low).unwrap();
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let m = sensor.measure_illuminated(&mut led, &mut delay).unwrap();
+//! if m.is_saturated() {
+//!     println!("Saturated. Use a higher range or a dimmer illuminator.");
+//! }
+//! let r = m.reflectance;
+//! println!("R: {}, G: {}, B: {}", r.red, r.green, r.blue);
+//! # }
+//! ```
+
+use crate::{ChannelCondition, Error, InterruptPinMode, Isl29125, Measurement};
+use embedded_hal::{
+    blocking::{delay::DelayUs, i2c},
+    digital::v2::OutputPin,
+};
+
+/// Illumination measurement error
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum IlluminationError<E, PE> {
+    /// Device error
+    Device(Error<E>),
+    /// Error setting the state of the illuminator pin
+    Pin(PE),
+}
+
+impl<E, PE> From<Error<E>> for IlluminationError<E, PE> {
+    fn from(e: Error<E>) -> Self {
+        IlluminationError::Device(e)
+    }
+}
+
+/// Ambient-subtracted measurement
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct IlluminatedMeasurement {
+    /// Illuminated minus ambient counts, clamped at zero
+    pub reflectance: Measurement,
+    /// Measurement with the illuminator off
+    pub ambient: Measurement,
+    /// Measurement with the illuminator on
+    pub illuminated: Measurement,
+    /// Red channel condition with the illuminator on
+    pub red: ChannelCondition,
+    /// Green channel condition with the illuminator on
+    pub green: ChannelCondition,
+    /// Blue channel condition with the illuminator on
+    pub blue: ChannelCondition,
+}
+
+impl IlluminatedMeasurement {
+    /// Compute the ambient-subtracted measurement from both readings.
+    ///
+    /// If the measurements were taken at different resolutions, the ambient
+    /// measurement is rescaled to the resolution of the illuminated one
+    /// before subtracting.
+    pub fn new(ambient: Measurement, illuminated: Measurement) -> Self {
+        let resolution = illuminated.resolution;
+        let scaled = ambient.to_resolution(resolution);
+        IlluminatedMeasurement {
+            reflectance: Measurement::new(
+                illuminated.red.saturating_sub(scaled.red),
+                illuminated.green.saturating_sub(scaled.green),
+                illuminated.blue.saturating_sub(scaled.blue),
+                resolution,
+            ),
+            ambient,
+            illuminated,
+            red: ChannelCondition::of(illuminated.red, resolution),
+            green: ChannelCondition::of(illuminated.green, resolution),
+            blue: ChannelCondition::of(illuminated.blue, resolution),
+        }
+    }
+
+    /// Whether any color channel is saturated with the illuminator on
+    ///
+    /// The reflectance of a saturated channel is underestimated.
+    pub fn is_saturated(&self) -> bool {
+        [self.red, self.green, self.blue].contains(&ChannelCondition::Saturated)
+    }
+}
+
+impl<I2C, E> Isl29125<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    /// Take an ambient-subtracted measurement with active illumination
+    ///
+    /// The illuminator is switched off by setting the pin low and a
+    /// measurement is taken. Then it is switched on by setting the pin high
+    /// and another measurement is taken. For each of them the ADC conversion
+    /// is restarted and a complete conversion cycle is waited. The
+    /// illuminator is switched off again afterwards, even if reading the data
+    /// fails.
+    ///
+    /// If reading the data fails and switching the illuminator off fails as
+    /// well, the read error is returned.
+    ///
+    /// The conversions are restarted by writing the configuration, which does
+    /// not start a conversion in `InterruptPinMode::SyncStart`. If the
+    /// interrupt pin is in that mode or no color channel is enabled in the
+    /// current operating mode, `Error::InvalidInputData` is returned.
+    /// Dimming the illuminator with PWM is not supported.
+    pub fn measure_illuminated<P, D>(
+        &mut self,
+        illuminator: &mut P,
+        delay: &mut D,
+    ) -> Result<IlluminatedMeasurement, IlluminationError<E, P::Error>>
+    where
+        P: OutputPin,
+        D: DelayUs<u32>,
+    {
+        let cycle_time = self.operating_mode().cycle_time_us(self.resolution());
+        if cycle_time == 0 || self.interrupt_pin_mode() == InterruptPinMode::SyncStart {
+            return Err(Error::InvalidInputData.into());
+        }
+        illuminator.set_low().map_err(IlluminationError::Pin)?;
+        let ambient = self.measure_after_restart(delay, cycle_time)?;
+        illuminator.set_high().map_err(IlluminationError::Pin)?;
+        let illuminated = self.measure_after_restart(delay, cycle_time);
+        let switched_off = illuminator.set_low().map_err(IlluminationError::Pin);
+        let illuminated = illuminated?;
+        switched_off?;
+        Ok(IlluminatedMeasurement::new(ambient, illuminated))
+    }
+
+    fn measure_after_restart<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        cycle_time: u32,
+    ) -> Result<Measurement, Error<E>> {
+        self.restart_conversion()?;
+        delay.delay_us(cycle_time);
+        self.read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Resolution;
+
+    #[test]
+    fn subtracts_ambient_clamping_at_zero() {
+        let ambient = Measurement::new(1000, 2000, 300, Resolution::Bit16);
+        let illuminated = Measurement::new(1500, 1500, 65535, Resolution::Bit16);
+        let m = IlluminatedMeasurement::new(ambient, illuminated);
+        assert_eq!(
+            Measurement::new(500, 0, 65235, Resolution::Bit16),
+            m.reflectance
+        );
+        assert_eq!(ChannelCondition::Normal, m.red);
+        assert_eq!(ChannelCondition::Saturated, m.blue);
+        assert!(m.is_saturated());
+    }
+
+    #[test]
+    fn rescales_ambient_to_illuminated_resolution() {
+        let ambient = Measurement::new(100, 200, 4095, Resolution::Bit12);
+        let illuminated = Measurement::new(3200, 6400, 65535, Resolution::Bit16);
+        let m = IlluminatedMeasurement::new(ambient, illuminated);
+        assert_eq!(
+            Measurement::new(1600, 3199, 0, Resolution::Bit16),
+            m.reflectance
+        );
+        assert_eq!(Resolution::Bit16, m.reflectance.resolution);
+        assert_eq!(Resolution::Bit12, m.ambient.resolution);
+    }
+}
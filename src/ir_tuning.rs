@@ -0,0 +1,146 @@
+use crate::{Error, IRFilteringRange, InterruptPinMode, Isl29125, Measurement, OperatingMode};
+use embedded_hal::blocking::{delay::DelayUs, i2c};
+
+impl<I2C, E> Isl29125<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    /// Tune the IR filtering so that the measured color proportions match a target
+    ///
+    /// The sensor must be looking at a reference light source with known IR
+    /// content and be configured in `OperatingMode::RedGreenBlue`. Otherwise
+    /// `Error::InvalidInputData` is returned.
+    ///
+    /// Only the proportions between the channels of `target` matter, so it can
+    /// be given in counts from a reference instrument or in any other scale
+    /// and its resolution is irrelevant.
+    /// The IR filtering setting for which the measured proportions are closest
+    /// to the target ones is applied and returned.
+    ///
+    /// See [`tune_ir_filtering_with()`](#method.tune_ir_filtering_with) for
+    /// details about the sweep.
+    pub fn tune_ir_filtering<D>(
+        &mut self,
+        delay: &mut D,
+        target: Measurement,
+    ) -> Result<IRFilteringRange, Error<E>>
+    where
+        D: DelayUs<u32>,
+    {
+        if self.operating_mode() != OperatingMode::RedGreenBlue || sum(&target) == 0 {
+            return Err(Error::InvalidInputData);
+        }
+        self.tune_ir_filtering_with(delay, |m| ratio_error(m, &target))
+    }
+
+    /// Tune the IR filtering minimizing a user-supplied objective function
+    ///
+    /// All IR filtering settings are swept from `IRFilteringRange::Lower(0)`
+    /// to `IRFilteringRange::Higher(63)`. For each of them the ADC conversion
+    /// is restarted and, after waiting for a complete conversion cycle for
+    /// the current operating mode and resolution, the colors are read and
+    /// passed to `objective`. The setting with the lowest objective value
+    /// (the first one in case of ties) is applied and returned.
+    ///
+    /// Note that the sweep takes 128 conversion cycles, which at 16-bit
+    /// resolution and in `OperatingMode::RedGreenBlue` is about 38 seconds.
+    ///
+    /// If an error occurs during the sweep, the previous IR filtering setting
+    /// is restored before returning the error.
+    ///
+    /// If no color channel is enabled in the current operating mode or the
+    /// interrupt pin is in `InterruptPinMode::SyncStart`, where conversions
+    /// cannot be restarted through I²C, `Error::InvalidInputData` is returned.
+    pub fn tune_ir_filtering_with<D, F>(
+        &mut self,
+        delay: &mut D,
+        objective: F,
+    ) -> Result<IRFilteringRange, Error<E>>
+    where
+        D: DelayUs<u32>,
+        F: FnMut(&Measurement) -> u64,
+    {
+        let cycle_time = self.operating_mode().cycle_time_us(self.resolution());
+        if cycle_time == 0 || self.interrupt_pin_mode() == InterruptPinMode::SyncStart {
+            return Err(Error::InvalidInputData);
+        }
+        let previous = self.ir_filtering();
+        let best = match self.sweep_ir_filtering(delay, cycle_time, objective) {
+            Ok(best) => best,
+            Err(e) => {
+                // The sweep error is reported even if restoring fails as well.
+                let _ = self.set_ir_filtering(previous);
+                return Err(e);
+            }
+        };
+        self.set_ir_filtering(best)?;
+        Ok(best)
+    }
+
+    fn sweep_ir_filtering<D, F>(
+        &mut self,
+        delay: &mut D,
+        cycle_time: u32,
+        mut objective: F,
+    ) -> Result<IRFilteringRange, Error<E>>
+    where
+        D: DelayUs<u32>,
+        F: FnMut(&Measurement) -> u64,
+    {
+        let settings = (0..64)
+            .map(IRFilteringRange::Lower)
+            .chain((0..64).map(IRFilteringRange::Higher));
+        let mut best = (u64::MAX, IRFilteringRange::default());
+        for setting in settings {
+            self.set_ir_filtering(setting)?;
+            self.restart_conversion()?;
+            delay.delay_us(cycle_time);
+            let measurement = self.read()?;
+            let value = objective(&measurement);
+            if value < best.0 {
+                best = (value, setting);
+            }
+        }
+        Ok(best.1)
+    }
+}
+
+fn sum(m: &Measurement) -> u64 {
+    u64::from(m.red) + u64::from(m.green) + u64::from(m.blue)
+}
+
+/// Difference between the color proportions of a measurement and a target.
+///
+/// Computed with integers as `Σ |m_c * Σt - t_c * Σm|` so that no division
+/// is necessary.
+fn ratio_error(m: &Measurement, target: &Measurement) -> u64 {
+    let (sum_m, sum_t) = (sum(m), sum(target));
+    let channel_error =
+        |m_c: u16, t_c: u16| (u64::from(m_c) * sum_t).abs_diff(u64::from(t_c) * sum_m);
+    channel_error(m.red, target.red)
+        + channel_error(m.green, target.green)
+        + channel_error(m.blue, target.blue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const fn new(red: u16, green: u16, blue: u16) -> Measurement {
+        Measurement::new(red, green, blue, crate::Resolution::Bit16)
+    }
+
+    #[test]
+    fn ratio_error_is_zero_for_same_proportions() {
+        assert_eq!(0, ratio_error(&new(100, 200, 300), &new(1, 2, 3)));
+    }
+
+    #[test]
+    fn ratio_error_grows_with_difference() {
+        let target = new(1, 1, 1);
+        let close = ratio_error(&new(100, 110, 100), &target);
+        let far = ratio_error(&new(100, 200, 100), &target);
+        assert!(close > 0);
+        assert!(far > close);
+    }
+}
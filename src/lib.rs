@@ -5,33 +5,94 @@
 //!
 //! This driver allows you to:
 //! - Read all colors. See: [`read()`].
+//! - Read all colors checking for saturation and under-range. See: [`read_checked()`].
 //! - Read red/green/blue colors individually. See: [`red()`].
 //! - Set operating mode. See: [`set_operating_mode()`].
 //! - Set ADC resolution. See: [`set_resolution()`].
 //! - Set RGB data sensing range. See: [`set_range()`].
 //! - Set IR filtering. See: [`set_ir_filtering()`].
+//! - Tune the IR filtering against a reference light source. See: [`tune_ir_filtering()`].
 //! - Read the status flags. See: [`status()`].
+//! - Iterate over continuous measurements. See: [`measurements()`].
+//! - Sample continuously into a ring buffer with rolling statistics. See: [`Sampler`].
+//! - Timestamp measurements with a pluggable clock. See: [`Timestamped`].
+//! - Log measurements to CSV or JSON lines files (`std` only). See: [`logger`].
+//! - Export measurements in the OpenMetrics format to Prometheus (`std` only).
+//!   See: [`metrics`].
+//! - Publish measurements over MQTT with Home Assistant discovery (`std` only).
+//!   See: [`mqtt`].
+//! - Emulate the Linux IIO sysfs interface with attribute files (`std` only).
+//!   See: [`iio`].
+//! - Record and replay the I²C traffic for regression tests (`std` only).
+//!   See: [`recording`].
+//! - Take duty-cycled measurements powering down in between. See: [`Scheduler`].
+//! - Filter measurement streams (moving average, exponential smoothing,
+//!   median and Kalman). See: [`filter`].
 //! - Clear the status flags. See: [`clear_status()`].
 //! - Read the device ID. See: [`device_id()`].
+//! - Compute conversion times, maximum counts and supply currents at compile
+//!   time. See: [`OperatingMode::cycle_time_us()`] and [`Resolution::max_count()`].
 //! - Perform a software reset. See: [`reset()`].
+//! - Apply a complete configuration at once. See: [`configure()`].
+//! - Use arrays of sensors behind a TCA9548A I2C multiplexer. See: [`SensorArray`].
+//! - Convert measurements to CIE XYZ, L\*a\*b\* and L\*u\*v\* and compute
+//!   color differences (CIE76, CIE94, CIEDE2000). See: [`color`].
+//! - Convert measurements to gamma-encoded 8-bit sRGB, HSV and HSL for
+//!   display. See: [`Srgb8`].
+//! - White balance against a captured white reference or automatically
+//!   (gray world, white patch). See: [`WhiteBalance`].
+//! - Detect the type of light source (daylight, incandescent, fluorescent,
+//!   LED) from the color temperature and IR content. See: [`illuminant`].
+//! - Take ambient-subtracted measurements with an illuminator controlled
+//!   through an output pin. See: [`measure_illuminated()`].
+//! - Classify measurements against a palette of reference colors. See: [`Palette`].
+//! - Detect threshold crossings in software on any channel, channel ratios,
+//!   illuminance and color temperature with hysteresis and persistence.
+//!   See: [`EventDetector`].
 //! - Interrupts:
 //!     - Set interrupt thresholds. See: [`set_interrupt_thresholds()`].
+//!     - Re-center the interrupt thresholds around the current light level to
+//!       be woken up only on significant changes. See: [`recenter_interrupt_thresholds()`].
 //!     - Set interrupt threshold assignment. See: [`set_interrupt_threshold_assignment()`].
 //!     - Set the fault count. See: [`set_fault_count()`].
 //!     - Set interrupt pin mode. See: [`set_interrupt_pin_mode()`].
 //!     - Enable/Disable generating an interrupt after a conversion is done. See: [`enable_interrupt_on_conversion_done()`].
 //!
 //! [`read()`]: struct.Isl29125.html#method.read
+//! [`read_checked()`]: struct.Isl29125.html#method.read_checked
 //! [`red()`]: struct.Isl29125.html#method.red
 //! [`set_operating_mode()`]: struct.Isl29125.html#method.set_operating_mode
 //! [`set_resolution()`]: struct.Isl29125.html#method.set_resolution
 //! [`set_range()`]: struct.Isl29125.html#method.set_range
 //! [`set_ir_filtering()`]: struct.Isl29125.html#method.set_ir_filtering
+//! [`tune_ir_filtering()`]: struct.Isl29125.html#method.tune_ir_filtering
 //! [`status()`]: struct.Isl29125.html#method.status
+//! [`measurements()`]: struct.Isl29125.html#method.measurements
+//! [`Sampler`]: sampler/struct.Sampler.html
+//! [`Timestamped`]: timestamp/struct.Timestamped.html
+//! [`logger`]: logger/index.html
+//! [`metrics`]: metrics/index.html
+//! [`mqtt`]: mqtt/index.html
+//! [`iio`]: iio/index.html
+//! [`recording`]: recording/index.html
+//! [`Scheduler`]: scheduler/struct.Scheduler.html
+//! [`filter`]: filter/index.html
 //! [`clear_status()`]: struct.Isl29125.html#method.clear_status
 //! [`device_id()`]: struct.Isl29125.html#method.device_id
+//! [`OperatingMode::cycle_time_us()`]: enum.OperatingMode.html#method.cycle_time_us
+//! [`Resolution::max_count()`]: enum.Resolution.html#method.max_count
 //! [`reset()`]: struct.Isl29125.html#method.reset
+//! [`configure()`]: struct.Isl29125.html#method.configure
+//! [`SensorArray`]: sensor_array/struct.SensorArray.html
+//! [`color`]: color/index.html
+//! [`Srgb8`]: color/struct.Srgb8.html
+//! [`WhiteBalance`]: white_balance/struct.WhiteBalance.html
+//! [`illuminant`]: illuminant/index.html
+//! [`measure_illuminated()`]: struct.Isl29125.html#method.measure_illuminated
+//! [`Palette`]: classify/struct.Palette.html
+//! [`EventDetector`]: events/struct.EventDetector.html
 //! [`set_interrupt_thresholds()`]: struct.Isl29125.html#method.set_interrupt_thresholds
+//! [`recenter_interrupt_thresholds()`]: struct.Isl29125.html#method.recenter_interrupt_thresholds
 //! [`set_interrupt_threshold_assignment()`]: struct.Isl29125.html#method.set_interrupt_threshold_assignment
 //! [`set_fault_count()`]: struct.Isl29125.html#method.set_fault_count
 //! [`set_interrupt_pin_mode()`]: struct.Isl29125.html#method.set_interrupt_pin_mode
@@ -42,6 +103,17 @@
 //! [Introductory blog post](TODO)
 //! -->
 //!
+//! ## Features
+//!
+//! - `defmt`: Implements `defmt::Format` for all public types and logs every
+//!   register read and write at trace level.
+//! - `rgb`: Implements conversion from `color::Srgb8` into `rgb::RGB8`.
+//! - `embedded-graphics-core`: Implements conversion from `color::Srgb8` into
+//!   `embedded_graphics_core::pixelcolor::Rgb888`.
+//! - `std`: Enables the parts of the crate that need the standard library, like
+//!   `timestamp::StdClock` and the `logger`, `metrics`, `mqtt`, `iio` and
+//!   `recording` modules.
+//!
 //! ## The device
 //!
 //! The ISL29125 is a low power, high sensitivity, RED, GREEN and BLUE color
@@ -150,16 +222,171 @@
 //! # }
 //! ```
 //!
+//! ### Tune the IR filtering against a reference light source
+//!
+//! The sensor is pointed at a light source with known IR content whose
+//! color proportions are known to be 1:1.2:0.9 (red:green:blue).
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{Isl29125, Measurement, OperatingMode, Resolution};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! let mut delay = hal::Delay;
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let target = Measurement::new(100, 120, 90, Resolution::Bit16);
+//! let ir_filtering = sensor.tune_ir_filtering(&mut delay, target).unwrap();
+//! println!("Selected IR filtering: {:?}", ir_filtering);
+//! # }
+//! ```
+//!
+//! ### Measure every 10 seconds powering down in between
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{scheduler::Scheduler, Isl29125, OperatingMode, Resolution};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! let mut delay = hal::Delay;
+//! let scheduler = Scheduler::new(OperatingMode::RedGreenBlue, 10_000_000);
+//! let current = scheduler.average_current_na(Resolution::Bit16);
+//! println!("Average current: {} nA", current);
+//! loop {
+//!     let m = scheduler.measure_and_wait(&mut sensor, &mut delay).unwrap();
+//!     println!("R: {}, G: {}, B: {}", m.red, m.green, m.blue);
+//! }
+//! # }
+//! ```
+//!
+//! ### Share the I2C bus with other devices
+//!
+//! [`Isl29125::new()`] takes ownership of the I2C bus, but it accepts any
+//! type implementing the `embedded-hal` 0.2 `Write` and `WriteRead` traits.
+//! To use several drivers on the same bus, give each of them a bus proxy
+//! like those provided by the [`shared-bus`] crate. The driver does not keep
+//! any state on the bus between calls, so the proxies can be used in any
+//! order. Note that the `embedded-hal-bus` devices implement the
+//! `embedded-hal` 1.0 traits and cannot be used with this driver.
+//!
+//! `embedded-hal` 0.2 does not implement its traits for `&mut I2C`. To lend
+//! a bus to the driver, wrap the reference in a [`BorrowedI2c`].
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use embedded_hal::blocking::i2c::Write;
+//! use isl29125::{Isl29125, OperatingMode};
+//! use shared_bus::BusManagerSimple;
+//!
+//! # fn main() {
+//! let bus = BusManagerSimple::new(hal::I2cdev::new("/dev/i2c-1").unwrap());
+//! let mut sensor = Isl29125::new(bus.acquire_i2c());
+//! let mut other_device = bus.acquire_i2c();
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! other_device.write(0x50, &[0x00, 0x12]).unwrap();
+//! # }
+//! ```
+//!
+//! [`Isl29125::new()`]: struct.Isl29125.html#method.new
+//! [`shared-bus`]: https://crates.io/crates/shared-bus
+//! [`BorrowedI2c`]: struct.BorrowedI2c.html
+//!
+//! ### Read an array of sensors behind a TCA9548A multiplexer
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{
+//!     sensor_array::{SensorArray, DEFAULT_MUX_ADDRESS},
+//!     OperatingMode, SensorConfig,
+//! };
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let channels = [0, 1, 2, 3, 4, 5, 6, 7];
+//! let mut sensors = SensorArray::new(dev, DEFAULT_MUX_ADDRESS, channels);
+//! let config = SensorConfig {
+//!     operating_mode: OperatingMode::RedGreenBlue,
+//!     ..SensorConfig::default()
+//! };
+//! sensors.configure(&config);
+//! for (i, result) in sensors.read_all().iter().enumerate() {
+//!     match result {
+//!         Ok(m) => println!("{}: R: {}, G: {}, B: {}", i, m.red, m.green, m.blue),
+//!         Err(e) => println!("{}: Error: {:?}", i, e),
+//!     }
+//! }
+//! # }
+//! ```
+//!
+//! ### Sample continuously and print the statistics of the last 16 samples
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{sampler::Sampler, Isl29125, OperatingMode};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let mut sampler = Sampler::<16>::new();
+//! let mut time_ms = 0;
+//! loop {
+//!     sampler.sample(&mut sensor, time_ms).unwrap();
+//!     let stats = sampler.statistics().unwrap();
+//!     println!("Red mean: {}, std dev: {}", stats.red.mean, stats.red.std_dev);
+//!     std::thread::sleep(std::time::Duration::from_millis(100));
+//!     time_ms += 100;
+//! }
+//! # }
+//! ```
+//!
 #![deny(unsafe_code, missing_docs)]
-#![no_std]
+// Default values of the enums are implemented by hand, as in the rest of the crate.
+#![allow(clippy::derivable_impls)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+mod borrowed_i2c;
+pub use crate::borrowed_i2c::BorrowedI2c;
+pub mod classify;
+pub mod color;
 mod device_impl;
+pub mod events;
+pub mod filter;
+#[cfg(feature = "std")]
+pub mod iio;
+pub mod illuminant;
+pub mod illumination;
+mod ir_tuning;
+#[cfg(feature = "std")]
+pub mod logger;
+pub mod measurements;
+#[cfg(feature = "std")]
+pub mod metrics;
+#[cfg(feature = "std")]
+pub mod mqtt;
+#[cfg(feature = "std")]
+pub mod recording;
 mod types;
 pub use crate::types::{
-    ConversionStatus, Error, FaultCount, IRFilteringRange, InterruptPinMode,
-    InterruptThresholdAssignment, Measurement, OperatingMode, Range, Resolution, Status,
+    ChannelCondition, CheckedMeasurement, ConversionStatus, Error, FaultCount, IRFilteringRange,
+    InterruptPinMode, InterruptThresholdAssignment, Measurement, OperatingMode, Range, Resolution,
+    SensorConfig, Status, ThresholdBand,
 };
 mod register_address;
+pub mod sampler;
+pub mod scheduler;
+pub mod sensor_array;
+pub mod timestamp;
+pub mod white_balance;
 use crate::register_address::{BitFlags, Register};
 
 /// ISL29125 device driver
@@ -167,7 +394,17 @@ use crate::register_address::{BitFlags, Register};
 pub struct Isl29125<I2C> {
     i2c: I2C,
     config1: Config,
+    config2: Config,
     config3: Config,
+    thresholds: Option<Thresholds>,
+}
+
+/// Interrupt thresholds as set by the user, with the resolution in effect then.
+#[derive(Debug, Clone, Copy)]
+struct Thresholds {
+    low: u16,
+    high: u16,
+    resolution: Resolution,
 }
 
 #[derive(Debug, Default, Clone, Copy)]
@@ -0,0 +1,451 @@
+//! Data logging to CSV or JSON lines files.
+//!
+//! Requires the `std` feature.
+//!
+//! A [`Logger`] reads the status and the measurement at a configured period
+//! and appends one record per sample to a file. Each record contains a
+//! timestamp in milliseconds, the raw counts, the resolution, range and
+//! operating mode in effect, the status flags and optionally the derived
+//! illuminance and correlated color temperature (CCT). Files can be rotated
+//! by size or by time.
+//!
+//! Files are named `<prefix>-<index>.csv` or `<prefix>-<index>.jsonl` with
+//! a four-digit index starting at zero. Existing files are never
+//! overwritten: if a file with the next index already exists, for example
+//! from a previous run, the following free index is used instead. CSV files
+//! begin with a header line.
+//!
+//! Note that reading the status clears the interrupt triggered flag and the
+//! interrupt output.
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{
+//!     logger::{Format, Logger, LoggerConfig, Rotation},
+//!     timestamp::SystemClock,
+//!     Isl29125, OperatingMode,
+//! };
+//! use std::time::Duration;
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let config = LoggerConfig::new(Format::Csv)
+//!     .with_period(Duration::from_millis(500))
+//!     .with_rotation(Rotation::Interval(Duration::from_secs(3600)))
+//!     .with_derived_values(true);
+//! let mut logger = Logger::new(sensor, SystemClock, config, "/tmp", "isl29125");
+//! logger.run(None).unwrap();
+//! # }
+//! ```
+//!
+//! [`Logger`]: struct.Logger.html
+
+use crate::{
+    color::Xyz,
+    timestamp::{Clock, Timestamped, TimestampedMeasurement},
+    Error, Isl29125, Resolution, Status,
+};
+use embedded_hal::blocking::i2c;
+use std::{
+    fmt::Write as _,
+    fs::{File, OpenOptions},
+    io::{self, Write as _},
+    path::PathBuf,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Record format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Comma-separated values with a header line (default)
+    Csv,
+    /// One JSON object per line
+    JsonLines,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Format::Csv
+    }
+}
+
+impl Format {
+    /// File name extension used for this format
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::JsonLines => "jsonl",
+        }
+    }
+}
+
+/// File rotation policy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rotation {
+    /// Write everything into a single file (default)
+    Never,
+    /// Start a new file before a record would make the current one exceed
+    /// this size in bytes
+    ///
+    /// A file always contains at least one record.
+    Size(u64),
+    /// Start a new file once this time has elapsed since the first record
+    /// of the current one
+    Interval(Duration),
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation::Never
+    }
+}
+
+/// Logger configuration
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoggerConfig {
+    format: Format,
+    rotation: Rotation,
+    derived_values: bool,
+    period: Duration,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self::new(Format::default())
+    }
+}
+
+impl LoggerConfig {
+    /// Create a configuration logging every second without rotation nor
+    /// derived values.
+    pub const fn new(format: Format) -> Self {
+        LoggerConfig {
+            format,
+            rotation: Rotation::Never,
+            derived_values: false,
+            period: Duration::from_secs(1),
+        }
+    }
+
+    /// Set the record format.
+    pub const fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Set the file rotation policy.
+    pub const fn with_rotation(mut self, rotation: Rotation) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Set whether to include the illuminance in lux and the CCT in Kelvin.
+    ///
+    /// The CCT is left empty if it cannot be computed.
+    pub const fn with_derived_values(mut self, enabled: bool) -> Self {
+        self.derived_values = enabled;
+        self
+    }
+
+    /// Set the time between samples used by [`Logger::run()`](struct.Logger.html#method.run).
+    pub const fn with_period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// Record format
+    pub const fn format(&self) -> Format {
+        self.format
+    }
+
+    /// File rotation policy
+    pub const fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+
+    /// Whether derived values are included
+    pub const fn derived_values(&self) -> bool {
+        self.derived_values
+    }
+
+    /// Time between samples
+    pub const fn period(&self) -> Duration {
+        self.period
+    }
+}
+
+/// Logger error
+#[derive(Debug)]
+pub enum LoggerError<E> {
+    /// Device error
+    Device(Error<E>),
+    /// File I/O error
+    Io(io::Error),
+}
+
+impl<E> From<Error<E>> for LoggerError<E> {
+    fn from(e: Error<E>) -> Self {
+        LoggerError::Device(e)
+    }
+}
+
+impl<E> From<io::Error> for LoggerError<E> {
+    fn from(e: io::Error) -> Self {
+        LoggerError::Io(e)
+    }
+}
+
+#[derive(Debug)]
+struct OpenFile {
+    file: File,
+    size: u64,
+    records: u32,
+    started: u64,
+}
+
+/// Data logger writing CSV or JSON lines files
+///
+/// The clock must return milliseconds, for example
+/// [`SystemClock`](../timestamp/struct.SystemClock.html).
+#[derive(Debug)]
+pub struct Logger<I2C, C> {
+    sensor: Timestamped<I2C, C>,
+    config: LoggerConfig,
+    directory: PathBuf,
+    prefix: String,
+    index: u32,
+    current: Option<OpenFile>,
+}
+
+impl<I2C, C> Logger<I2C, C> {
+    /// Create a new logger writing into `directory`.
+    ///
+    /// No file is created until the first record is written.
+    pub fn new(
+        sensor: Isl29125<I2C>,
+        clock: C,
+        config: LoggerConfig,
+        directory: impl Into<PathBuf>,
+        prefix: &str,
+    ) -> Self {
+        Logger {
+            sensor: Timestamped::new(sensor, clock),
+            config,
+            directory: directory.into(),
+            prefix: prefix.into(),
+            index: 0,
+            current: None,
+        }
+    }
+
+    /// Destroy the logger and return the driver and the clock.
+    pub fn destroy(self) -> (Isl29125<I2C>, C) {
+        self.sensor.destroy()
+    }
+
+    /// Access the driver, for example to change its configuration.
+    pub fn sensor(&mut self) -> &mut Isl29125<I2C> {
+        self.sensor.sensor()
+    }
+
+    /// Path of the file currently being written, if any
+    pub fn current_path(&self) -> Option<PathBuf> {
+        self.current.as_ref().map(|_| self.path(self.index))
+    }
+
+    fn path(&self, index: u32) -> PathBuf {
+        self.directory.join(format!(
+            "{}-{:04}.{}",
+            self.prefix,
+            index,
+            self.config.format.extension()
+        ))
+    }
+
+    fn needs_rotation(&self, timestamp: u64, length: u64) -> bool {
+        let current = match &self.current {
+            Some(current) if current.records > 0 => current,
+            _ => return false,
+        };
+        match self.config.rotation {
+            Rotation::Never => false,
+            Rotation::Size(max) => current.size + length > max,
+            Rotation::Interval(interval) => {
+                u128::from(timestamp.saturating_sub(current.started)) >= interval.as_millis()
+            }
+        }
+    }
+
+    /// Create the file with the current index or, if it already exists, with
+    /// the next free one.
+    fn create_next_file(&mut self) -> io::Result<File> {
+        loop {
+            let path = self.path(self.index);
+            match OpenOptions::new().write(true).create_new(true).open(path) {
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => self.index += 1,
+                result => return result,
+            }
+        }
+    }
+
+    fn write_line(&mut self, timestamp: u64, line: &str) -> io::Result<()> {
+        if self.needs_rotation(timestamp, line.len() as u64) {
+            self.current = None;
+            self.index += 1;
+        }
+        if self.current.is_none() {
+            let mut file = self.create_next_file()?;
+            let mut size = 0;
+            if self.config.format == Format::Csv {
+                let header = csv_header(self.config.derived_values);
+                file.write_all(header.as_bytes())?;
+                size = header.len() as u64;
+            }
+            self.current = Some(OpenFile {
+                file,
+                size,
+                records: 0,
+                started: timestamp,
+            });
+        }
+        if let Some(current) = &mut self.current {
+            current.file.write_all(line.as_bytes())?;
+            current.size += line.len() as u64;
+            current.records += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<I2C, E, C> Logger<I2C, C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    C: Clock<Instant = u64>,
+{
+    /// Read the status and a measurement and write a record.
+    pub fn log(&mut self) -> Result<(), LoggerError<E>> {
+        let status = self.sensor.sensor().status()?;
+        let m = self.sensor.read()?;
+        let line = match self.config.format {
+            Format::Csv => csv_record(&m, &status, self.config.derived_values),
+            Format::JsonLines => json_record(&m, &status, self.config.derived_values),
+        };
+        self.write_line(m.timestamp, &line)?;
+        Ok(())
+    }
+
+    /// Write `samples` records, or keep logging forever if `None`, one
+    /// every configured period.
+    ///
+    /// The records are scheduled at fixed intervals from the start, so the
+    /// time spent reading the sensor and writing the files does not add up
+    /// over a long run. If writing a record takes longer than the period, the
+    /// next one is written immediately and the schedule continues from
+    /// there. Missed records are not made up for.
+    ///
+    /// Stops at the first error.
+    pub fn run(&mut self, samples: Option<usize>) -> Result<(), LoggerError<E>> {
+        let mut remaining = samples;
+        let mut next = Instant::now();
+        while remaining != Some(0) {
+            self.log()?;
+            if let Some(n) = &mut remaining {
+                *n -= 1;
+                if *n == 0 {
+                    break;
+                }
+            }
+            next += self.config.period;
+            let now = Instant::now();
+            match next.checked_duration_since(now) {
+                Some(wait) => thread::sleep(wait),
+                None => next = now,
+            }
+        }
+        Ok(())
+    }
+}
+
+const CSV_COLUMNS: &str = "timestamp_ms,red,green,blue,resolution,range,mode,\
+                           interrupt_triggered,conversion_completed,brownout";
+
+fn csv_header(derived_values: bool) -> String {
+    if derived_values {
+        format!("{},lux,cct\n", CSV_COLUMNS)
+    } else {
+        format!("{}\n", CSV_COLUMNS)
+    }
+}
+
+fn resolution_bits(resolution: Resolution) -> u8 {
+    match resolution {
+        Resolution::Bit12 => 12,
+        Resolution::Bit16 => 16,
+    }
+}
+
+fn derived(m: &TimestampedMeasurement<u64>) -> (f32, Option<f32>) {
+    let lux = m.measurement.lux(m.range);
+    let cct = Xyz::from_measurement(&m.measurement).cct();
+    (lux, cct)
+}
+
+fn csv_record(m: &TimestampedMeasurement<u64>, status: &Status, derived_values: bool) -> String {
+    let mut line = format!(
+        "{},{},{},{},{},{},{:?},{},{},{}",
+        m.timestamp,
+        m.measurement.red,
+        m.measurement.green,
+        m.measurement.blue,
+        resolution_bits(m.resolution()),
+        m.range.full_scale_lux(),
+        m.operating_mode,
+        u8::from(status.interrupt_triggered),
+        u8::from(status.conversion_completed),
+        u8::from(status.brownout),
+    );
+    if derived_values {
+        let (lux, cct) = derived(m);
+        let _ = write!(line, ",{:.2},", lux);
+        if let Some(cct) = cct {
+            let _ = write!(line, "{:.0}", cct);
+        }
+    }
+    line.push('\n');
+    line
+}
+
+fn json_record(m: &TimestampedMeasurement<u64>, status: &Status, derived_values: bool) -> String {
+    let mut line = format!(
+        "{{\"timestamp_ms\":{},\"red\":{},\"green\":{},\"blue\":{},\"resolution\":{},\
+         \"range\":{},\"mode\":\"{:?}\",\"interrupt_triggered\":{},\
+         \"conversion_completed\":{},\"brownout\":{}",
+        m.timestamp,
+        m.measurement.red,
+        m.measurement.green,
+        m.measurement.blue,
+        resolution_bits(m.resolution()),
+        m.range.full_scale_lux(),
+        m.operating_mode,
+        status.interrupt_triggered,
+        status.conversion_completed,
+        status.brownout,
+    );
+    if derived_values {
+        let (lux, cct) = derived(m);
+        let _ = write!(line, ",\"lux\":{:.2},\"cct\":", lux);
+        match cct {
+            Some(cct) => {
+                let _ = write!(line, "{:.0}", cct);
+            }
+            None => line.push_str("null"),
+        }
+    }
+    line.push_str("}\n");
+    line
+}
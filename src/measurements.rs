@@ -0,0 +1,214 @@
+//! Blocking iterators over continuous measurements.
+//!
+//! [`measurements()`] borrows the driver and [`into_measurements()`] takes
+//! ownership of it. Both yield `Result<Measurement, Error<E>>` items paced
+//! according to a [`Pacing`] and end after the first error.
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{measurements::Pacing, Isl29125, OperatingMode};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! let mut delay = hal::Delay;
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! for m in sensor.measurements(&mut delay, Pacing::ConversionTime).take(10) {
+//!     let m = m.unwrap();
+//!     println!("R: {}, G: {}, B: {}", m.red, m.green, m.blue);
+//! }
+//! # }
+//! ```
+//!
+//! [`measurements()`]: ../struct.Isl29125.html#method.measurements
+//! [`into_measurements()`]: ../struct.Isl29125.html#method.into_measurements
+//! [`Pacing`]: enum.Pacing.html
+
+use crate::{Error, InterruptPinMode, Isl29125, Measurement};
+use core::iter::FusedIterator;
+use embedded_hal::blocking::{delay::DelayUs, i2c};
+
+/// How to wait for each new measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Pacing {
+    /// Wait for a complete conversion cycle of all enabled channels at the
+    /// current resolution before each read (default)
+    ConversionTime,
+    /// Poll the status every `poll_interval_us` microseconds until a
+    /// conversion has completed, then clear the status flags and read.
+    ///
+    /// If no conversion has completed after two conversion cycles,
+    /// `Error::Timeout` is returned. A `poll_interval_us` of zero would read
+    /// the status back to back, so `Error::InvalidInputData` is returned
+    /// instead.
+    ConversionDone {
+        /// Time between status reads in microseconds. Must be greater than zero.
+        poll_interval_us: u32,
+    },
+}
+
+impl Default for Pacing {
+    fn default() -> Self {
+        Pacing::ConversionTime
+    }
+}
+
+/// Iterator over measurements borrowing the driver
+///
+/// Created with [`measurements()`](../struct.Isl29125.html#method.measurements).
+#[derive(Debug)]
+pub struct Measurements<'a, I2C, D> {
+    sensor: &'a mut Isl29125<I2C>,
+    delay: &'a mut D,
+    pacing: Pacing,
+    done: bool,
+}
+
+/// Iterator over measurements owning the driver
+///
+/// Created with [`into_measurements()`](../struct.Isl29125.html#method.into_measurements).
+/// The driver and delay can be recovered with [`into_inner()`](#method.into_inner).
+#[derive(Debug)]
+pub struct IntoMeasurements<I2C, D> {
+    sensor: Isl29125<I2C>,
+    delay: D,
+    pacing: Pacing,
+    done: bool,
+}
+
+impl<I2C, D> IntoMeasurements<I2C, D> {
+    /// Return the driver and the delay.
+    pub fn into_inner(self) -> (Isl29125<I2C>, D) {
+        (self.sensor, self.delay)
+    }
+}
+
+impl<I2C, E> Isl29125<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    /// Iterate over continuous measurements borrowing the driver.
+    ///
+    /// See the [`measurements`](measurements/index.html) module.
+    pub fn measurements<'a, D: DelayUs<u32>>(
+        &'a mut self,
+        delay: &'a mut D,
+        pacing: Pacing,
+    ) -> Measurements<'a, I2C, D> {
+        Measurements {
+            sensor: self,
+            delay,
+            pacing,
+            done: false,
+        }
+    }
+
+    /// Iterate over continuous measurements taking ownership of the driver.
+    ///
+    /// See the [`measurements`](measurements/index.html) module.
+    pub fn into_measurements<D: DelayUs<u32>>(
+        self,
+        delay: D,
+        pacing: Pacing,
+    ) -> IntoMeasurements<I2C, D> {
+        IntoMeasurements {
+            sensor: self,
+            delay,
+            pacing,
+            done: false,
+        }
+    }
+
+    fn next_measurement<D: DelayUs<u32>>(
+        &mut self,
+        delay: &mut D,
+        pacing: Pacing,
+    ) -> Result<Measurement, Error<E>> {
+        let cycle_time = self.operating_mode().cycle_time_us(self.resolution());
+        if cycle_time == 0 || self.interrupt_pin_mode() == InterruptPinMode::SyncStart {
+            return Err(Error::InvalidInputData);
+        }
+        match pacing {
+            Pacing::ConversionTime => delay.delay_us(cycle_time),
+            Pacing::ConversionDone {
+                poll_interval_us: 0,
+            } => {
+                return Err(Error::InvalidInputData);
+            }
+            Pacing::ConversionDone { poll_interval_us } => {
+                let timeout = cycle_time.saturating_mul(2);
+                let mut waited: u32 = 0;
+                while !self.status()?.conversion_completed {
+                    if waited >= timeout {
+                        return Err(Error::Timeout);
+                    }
+                    delay.delay_us(poll_interval_us);
+                    waited = waited.saturating_add(poll_interval_us);
+                }
+                self.clear_status()?;
+            }
+        }
+        self.read()
+    }
+}
+
+impl<'a, I2C, D, E> Iterator for Measurements<'a, I2C, D>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    D: DelayUs<u32>,
+{
+    type Item = Result<Measurement, Error<E>>;
+
+    /// Wait for and read the next measurement.
+    ///
+    /// Returns `None` after an error has been returned. If no color channel
+    /// is enabled in the current operating mode or the interrupt pin is in
+    /// `InterruptPinMode::SyncStart`, `Error::InvalidInputData` is returned.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.sensor.next_measurement(self.delay, self.pacing);
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+impl<'a, I2C, D, E> FusedIterator for Measurements<'a, I2C, D>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    D: DelayUs<u32>,
+{
+}
+
+impl<I2C, D, E> Iterator for IntoMeasurements<I2C, D>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    D: DelayUs<u32>,
+{
+    type Item = Result<Measurement, Error<E>>;
+
+    /// Wait for and read the next measurement.
+    ///
+    /// Returns `None` after an error has been returned. If no color channel
+    /// is enabled in the current operating mode or the interrupt pin is in
+    /// `InterruptPinMode::SyncStart`, `Error::InvalidInputData` is returned.
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.sensor.next_measurement(&mut self.delay, self.pacing);
+        self.done = result.is_err();
+        Some(result)
+    }
+}
+
+impl<I2C, D, E> FusedIterator for IntoMeasurements<I2C, D>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    D: DelayUs<u32>,
+{
+}
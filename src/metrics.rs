@@ -0,0 +1,305 @@
+//! OpenMetrics exporter for Prometheus.
+//!
+//! Requires the `std` feature.
+//!
+//! An [`Exporter`] keeps the last measurement together with brownout and I²C
+//! error counters. They can be written atomically to a file for the
+//! node_exporter textfile collector in the Prometheus text format 0.0.4 or
+//! served over HTTP in the OpenMetrics text format.
+//!
+//! Rendered metrics:
+//! - `isl29125_channel_counts{channel="red|green|blue"}`: Raw color counts.
+//! - `isl29125_illuminance_lux`: Approximate illuminance.
+//! - `isl29125_cct_kelvin`: Correlated color temperature, when available.
+//! - `isl29125_brownouts_total`: Number of brownout conditions detected.
+//! - `isl29125_i2c_errors_total`: Number of failed updates due to I²C errors.
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{metrics::Exporter, Isl29125, OperatingMode};
+//! use std::{thread, time::Duration};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let mut exporter = Exporter::new(sensor).with_label("room", "greenhouse");
+//! loop {
+//!     exporter.update().ok();
+//!     exporter
+//!         .write_textfile("/var/lib/node_exporter/isl29125.prom")
+//!         .unwrap();
+//!     thread::sleep(Duration::from_secs(15));
+//! }
+//! # }
+//! ```
+//!
+//! [`Exporter`]: struct.Exporter.html
+
+use crate::{color::Xyz, Error, Isl29125, Measurement, Range};
+use embedded_hal::blocking::i2c;
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+/// Content type of the rendered metrics
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// OpenMetrics exporter
+#[derive(Debug)]
+pub struct Exporter<I2C> {
+    sensor: Isl29125<I2C>,
+    labels: Vec<(String, String)>,
+    last: Option<(Measurement, Range)>,
+    brownouts: u64,
+    i2c_errors: u64,
+}
+
+impl<I2C> Exporter<I2C> {
+    /// Create a new exporter without labels.
+    pub fn new(sensor: Isl29125<I2C>) -> Self {
+        Exporter {
+            sensor,
+            labels: Vec::new(),
+            last: None,
+            brownouts: 0,
+            i2c_errors: 0,
+        }
+    }
+
+    /// Add a label to all metrics.
+    ///
+    /// The name should match `[a-zA-Z_][a-zA-Z0-9_]*`. The value is escaped.
+    pub fn with_label(mut self, name: &str, value: &str) -> Self {
+        self.labels.push((name.into(), value.into()));
+        self
+    }
+
+    /// Destroy the exporter and return the driver.
+    pub fn destroy(self) -> Isl29125<I2C> {
+        self.sensor
+    }
+
+    /// Access the driver, for example to change its configuration.
+    pub fn sensor(&mut self) -> &mut Isl29125<I2C> {
+        &mut self.sensor
+    }
+
+    /// Number of brownout conditions detected so far
+    pub fn brownouts(&self) -> u64 {
+        self.brownouts
+    }
+
+    /// Number of updates failed due to I²C errors so far
+    pub fn i2c_errors(&self) -> u64 {
+        self.i2c_errors
+    }
+
+    /// Render the metrics in the OpenMetrics text format.
+    pub fn render(&self) -> String {
+        self.render_as(Format::OpenMetrics)
+    }
+
+    /// Render the metrics in the Prometheus text format 0.0.4.
+    ///
+    /// This is the format expected by the node_exporter textfile collector.
+    pub fn render_prometheus(&self) -> String {
+        self.render_as(Format::Prometheus)
+    }
+
+    fn render_as(&self, format: Format) -> String {
+        let mut out = String::new();
+        let openmetrics = format == Format::OpenMetrics;
+        if let Some((m, range)) = self.last {
+            family(
+                &mut out,
+                "isl29125_channel_counts",
+                "gauge",
+                "Raw color channel counts.",
+            );
+            for (channel, value) in [("red", m.red), ("green", m.green), ("blue", m.blue)] {
+                self.sample(
+                    &mut out,
+                    "isl29125_channel_counts",
+                    Some(("channel", channel)),
+                    value,
+                );
+            }
+            family(
+                &mut out,
+                "isl29125_illuminance_lux",
+                "gauge",
+                "Approximate illuminance.",
+            );
+            if openmetrics {
+                let _ = writeln!(out, "# UNIT isl29125_illuminance_lux lux");
+            }
+            self.sample(&mut out, "isl29125_illuminance_lux", None, m.lux(range));
+            if let Some(cct) = Xyz::from_measurement(&m).cct() {
+                family(
+                    &mut out,
+                    "isl29125_cct_kelvin",
+                    "gauge",
+                    "Correlated color temperature.",
+                );
+                if openmetrics {
+                    let _ = writeln!(out, "# UNIT isl29125_cct_kelvin kelvin");
+                }
+                self.sample(&mut out, "isl29125_cct_kelvin", None, cct);
+            }
+        }
+        // OpenMetrics names counter families without the `_total` suffix
+        // of their samples, Prometheus 0.0.4 uses the sample name.
+        let (brownouts, i2c_errors) = if openmetrics {
+            ("isl29125_brownouts", "isl29125_i2c_errors")
+        } else {
+            ("isl29125_brownouts_total", "isl29125_i2c_errors_total")
+        };
+        family(
+            &mut out,
+            brownouts,
+            "counter",
+            "Brownout conditions detected.",
+        );
+        self.sample(&mut out, "isl29125_brownouts_total", None, self.brownouts);
+        family(
+            &mut out,
+            i2c_errors,
+            "counter",
+            "Updates failed due to I2C errors.",
+        );
+        self.sample(&mut out, "isl29125_i2c_errors_total", None, self.i2c_errors);
+        if openmetrics {
+            out.push_str("# EOF\n");
+        }
+        out
+    }
+
+    /// Write the metrics to `path` atomically.
+    ///
+    /// The metrics are rendered in the Prometheus text format 0.0.4 and
+    /// written into a temporary file in the same directory which is then
+    /// renamed, so readers never see a partially written file.
+    pub fn write_textfile<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        fs::write(&tmp, self.render_prometheus())?;
+        fs::rename(&tmp, path)
+    }
+
+    /// Answer a single HTTP request on `stream`.
+    ///
+    /// `GET /metrics` is answered with the metrics in the OpenMetrics text
+    /// format and anything else with `404 Not Found`. The connection is
+    /// closed afterwards.
+    ///
+    /// This blocks until the request headers have been received. For a
+    /// `TcpStream`, set read and write timeouts before calling this so that
+    /// a client which stops sending cannot stall the caller.
+    pub fn serve_http<S: Read + Write>(&self, mut stream: S) -> io::Result<()> {
+        let mut request = Vec::new();
+        let mut buffer = [0; 512];
+        while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+            let n = stream.read(&mut buffer)?;
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buffer[..n]);
+        }
+        let found = request.starts_with(b"GET /metrics ") || request.starts_with(b"GET / ");
+        let (status, content_type, body) = if found {
+            ("200 OK", CONTENT_TYPE, self.render())
+        } else {
+            ("404 Not Found", "text/plain", String::from("Not Found\n"))
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+
+    fn sample<V: core::fmt::Display>(
+        &self,
+        out: &mut String,
+        name: &str,
+        extra: Option<(&str, &str)>,
+        value: V,
+    ) {
+        out.push_str(name);
+        let labels = self
+            .labels
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .chain(extra);
+        for (i, (label, label_value)) in labels.enumerate() {
+            out.push(if i == 0 { '{' } else { ',' });
+            let _ = write!(out, "{}=\"{}\"", label, escape(label_value));
+        }
+        if !self.labels.is_empty() || extra.is_some() {
+            out.push('}');
+        }
+        let _ = writeln!(out, " {}", value);
+    }
+}
+
+impl<I2C, E> Exporter<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    /// Read the status and a new measurement.
+    ///
+    /// A detected brownout condition is counted and cleared. I²C errors are
+    /// counted and returned. The previous measurement is kept on error.
+    pub fn update(&mut self) -> Result<(), Error<E>> {
+        let result = self.try_update();
+        if let Err(Error::I2C(_)) = result {
+            self.i2c_errors += 1;
+        }
+        result
+    }
+
+    fn try_update(&mut self) -> Result<(), Error<E>> {
+        if self.sensor.status()?.brownout {
+            self.brownouts += 1;
+            self.sensor.clear_status()?;
+        }
+        let measurement = self.sensor.read()?;
+        self.last = Some((measurement, self.sensor.range()));
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    OpenMetrics,
+    Prometheus,
+}
+
+fn family(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,617 @@
+//! MQTT publisher with Home Assistant discovery.
+//!
+//! Requires the `std` feature.
+//!
+//! A [`Publisher`] connects to an MQTT broker using a minimal MQTT 3.1.1
+//! client and publishes the red, green and blue counts, the approximate
+//! illuminance in lux and the correlated color temperature in Kelvin to
+//! `<base topic>/<red|green|blue|illuminance|color_temperature>`. Messages
+//! are sent with QoS 0. While idle, a PINGREQ is sent every keep alive
+//! interval so that the broker does not close the connection.
+//!
+//! [`publish_discovery()`] sends retained Home Assistant MQTT discovery
+//! config messages to
+//! `<discovery prefix>/sensor/<client id>/<red|green|...>/config`, so that
+//! the sensor appears automatically as a device with five entities.
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{
+//!     mqtt::{MqttConfig, Publisher},
+//!     Isl29125, OperatingMode,
+//! };
+//! use std::time::Duration;
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let config = MqttConfig::new("localhost", "isl29125_livingroom")
+//!     .with_credentials("user", "password")
+//!     .with_device_name("Living room light sensor");
+//! let mut publisher = Publisher::new(sensor, config);
+//! publisher.connect().unwrap();
+//! publisher.publish_discovery().unwrap();
+//! publisher.run(Duration::from_secs(30)).unwrap();
+//! # }
+//! ```
+//!
+//! [`Publisher`]: struct.Publisher.html
+//! [`publish_discovery()`]: struct.Publisher.html#method.publish_discovery
+
+use crate::{color::Xyz, Error, Isl29125};
+use embedded_hal::blocking::i2c;
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// MQTT connection and topic configuration
+///
+/// The password is not shown in the `Debug` output.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MqttConfig {
+    host: String,
+    port: u16,
+    client_id: String,
+    username: Option<String>,
+    password: Option<String>,
+    keep_alive_s: u16,
+    timeout: Duration,
+    base_topic: String,
+    discovery_prefix: String,
+    device_name: String,
+}
+
+impl MqttConfig {
+    /// Create a configuration for the broker at `host` on port 1883.
+    ///
+    /// The client ID is also used as the Home Assistant device identifier
+    /// and in the default base topic `isl29125/<client id>`.
+    pub fn new(host: &str, client_id: &str) -> Self {
+        MqttConfig {
+            host: host.into(),
+            port: 1883,
+            client_id: client_id.into(),
+            username: None,
+            password: None,
+            keep_alive_s: 60,
+            timeout: Duration::from_secs(10),
+            base_topic: format!("isl29125/{}", client_id),
+            discovery_prefix: "homeassistant".into(),
+            device_name: "ISL29125".into(),
+        }
+    }
+
+    /// Set the broker port.
+    pub fn with_port(mut self, port: u16) -> Self {
+        self.port = port;
+        self
+    }
+
+    /// Set the user name and password.
+    pub fn with_credentials(mut self, username: &str, password: &str) -> Self {
+        self.username = Some(username.into());
+        self.password = Some(password.into());
+        self
+    }
+
+    /// Set the keep alive interval in seconds (default: 60).
+    ///
+    /// The broker closes the connection if nothing is received within one
+    /// and a half times this interval, so a PINGREQ is sent whenever nothing
+    /// else has been sent for this long. Zero disables it.
+    pub fn with_keep_alive(mut self, seconds: u16) -> Self {
+        self.keep_alive_s = seconds;
+        self
+    }
+
+    /// Set the timeout for connecting and for each network read and write
+    /// (default: 10 seconds).
+    ///
+    /// A zero timeout is invalid and makes connecting fail.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the topic under which the values are published.
+    pub fn with_base_topic(mut self, topic: &str) -> Self {
+        self.base_topic = topic.into();
+        self
+    }
+
+    /// Set the Home Assistant discovery prefix (default: `homeassistant`).
+    pub fn with_discovery_prefix(mut self, prefix: &str) -> Self {
+        self.discovery_prefix = prefix.into();
+        self
+    }
+
+    /// Set the device name shown in Home Assistant (default: `ISL29125`).
+    pub fn with_device_name(mut self, name: &str) -> Self {
+        self.device_name = name.into();
+        self
+    }
+
+    /// Topic under which the values are published
+    pub fn base_topic(&self) -> &str {
+        &self.base_topic
+    }
+
+    fn keep_alive(&self) -> Option<Duration> {
+        match self.keep_alive_s {
+            0 => None,
+            seconds => Some(Duration::from_secs(u64::from(seconds))),
+        }
+    }
+}
+
+impl fmt::Debug for MqttConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MqttConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("client_id", &self.client_id)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .field("keep_alive_s", &self.keep_alive_s)
+            .field("timeout", &self.timeout)
+            .field("base_topic", &self.base_topic)
+            .field("discovery_prefix", &self.discovery_prefix)
+            .field("device_name", &self.device_name)
+            .finish()
+    }
+}
+
+/// Publisher error
+#[derive(Debug)]
+pub enum MqttError<E> {
+    /// Device error
+    Device(Error<E>),
+    /// Network error or connection refused by the broker
+    Io(io::Error),
+}
+
+impl<E> From<Error<E>> for MqttError<E> {
+    fn from(e: Error<E>) -> Self {
+        MqttError::Device(e)
+    }
+}
+
+impl<E> From<io::Error> for MqttError<E> {
+    fn from(e: io::Error) -> Self {
+        MqttError::Io(e)
+    }
+}
+
+struct Entity {
+    key: &'static str,
+    name: &'static str,
+    unit: Option<&'static str>,
+    device_class: Option<&'static str>,
+}
+
+const ENTITIES: [Entity; 5] = [
+    Entity {
+        key: "red",
+        name: "Red",
+        unit: None,
+        device_class: None,
+    },
+    Entity {
+        key: "green",
+        name: "Green",
+        unit: None,
+        device_class: None,
+    },
+    Entity {
+        key: "blue",
+        name: "Blue",
+        unit: None,
+        device_class: None,
+    },
+    Entity {
+        key: "illuminance",
+        name: "Illuminance",
+        unit: Some("lx"),
+        device_class: Some("illuminance"),
+    },
+    Entity {
+        key: "color_temperature",
+        name: "Color temperature",
+        unit: Some("K"),
+        device_class: None,
+    },
+];
+
+/// MQTT publisher
+#[derive(Debug)]
+pub struct Publisher<I2C> {
+    sensor: Isl29125<I2C>,
+    config: MqttConfig,
+    stream: Option<TcpStream>,
+    last_sent: Instant,
+}
+
+impl<I2C> Publisher<I2C> {
+    /// Create a new publisher. Call [`connect()`](#method.connect) before
+    /// publishing.
+    pub fn new(sensor: Isl29125<I2C>, config: MqttConfig) -> Self {
+        Publisher {
+            sensor,
+            config,
+            stream: None,
+            last_sent: Instant::now(),
+        }
+    }
+
+    /// Destroy the publisher and return the driver.
+    ///
+    /// The connection is closed without notifying the broker.
+    pub fn destroy(self) -> Isl29125<I2C> {
+        self.sensor
+    }
+
+    /// Connect to the broker, closing the current connection if any.
+    ///
+    /// Fails with `io::ErrorKind::ConnectionRefused` if the broker rejects
+    /// the connection. Fails as well if connecting or waiting for the answer
+    /// of the broker takes longer than the configured timeout.
+    pub fn connect(&mut self) -> io::Result<()> {
+        self.stream = None;
+        let mut stream = self.open_stream()?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(self.config.timeout))?;
+        stream.set_write_timeout(Some(self.config.timeout))?;
+        stream.write_all(&connect_packet(&self.config))?;
+        let mut connack = [0; 4];
+        stream.read_exact(&mut connack)?;
+        if connack[0] != 0x20 || connack[1] != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unexpected response to CONNECT",
+            ));
+        }
+        if connack[3] != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::ConnectionRefused,
+                format!("connection refused with return code {}", connack[3]),
+            ));
+        }
+        self.stream = Some(stream);
+        self.last_sent = Instant::now();
+        Ok(())
+    }
+
+    fn open_stream(&self) -> io::Result<TcpStream> {
+        let mut last_error = None;
+        for address in (self.config.host.as_str(), self.config.port).to_socket_addrs()? {
+            match TcpStream::connect_timeout(&address, self.config.timeout) {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "host name did not resolve")
+        }))
+    }
+
+    /// Disconnect from the broker.
+    pub fn disconnect(&mut self) -> io::Result<()> {
+        match self.stream.take() {
+            Some(mut stream) => stream.write_all(&[0xE0, 0]),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether a connection has been established
+    pub fn is_connected(&self) -> bool {
+        self.stream.is_some()
+    }
+
+    /// Access the driver, for example to change its configuration.
+    pub fn sensor(&mut self) -> &mut Isl29125<I2C> {
+        &mut self.sensor
+    }
+
+    /// Configuration
+    pub fn config(&self) -> &MqttConfig {
+        &self.config
+    }
+
+    /// Publish the retained Home Assistant discovery config messages.
+    pub fn publish_discovery(&mut self) -> io::Result<()> {
+        for entity in &ENTITIES {
+            let (topic, payload) = discovery_message(&self.config, entity);
+            self.publish_message(&topic, payload.as_bytes(), true)?;
+        }
+        Ok(())
+    }
+
+    /// Publish a message with QoS 0.
+    ///
+    /// Fails with `io::ErrorKind::NotConnected` if not connected. The
+    /// connection is dropped after a write error.
+    pub fn publish_message(&mut self, topic: &str, payload: &[u8], retain: bool) -> io::Result<()> {
+        self.send(&publish_packet(topic, payload, retain))
+    }
+
+    /// Send a PINGREQ and wait for the PINGRESP of the broker.
+    ///
+    /// Fails with `io::ErrorKind::NotConnected` if not connected. The
+    /// connection is dropped after an error.
+    pub fn ping(&mut self) -> io::Result<()> {
+        self.send(&[0xC0, 0])?;
+        let mut pingresp = [0; 2];
+        let result = match &mut self.stream {
+            Some(stream) => stream.read_exact(&mut pingresp),
+            None => Err(io::Error::from(io::ErrorKind::NotConnected)),
+        };
+        let result = result.and_then(|_| {
+            if pingresp == [0xD0, 0] {
+                Ok(())
+            } else {
+                Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unexpected response to PINGREQ",
+                ))
+            }
+        });
+        if result.is_err() {
+            self.stream = None;
+        }
+        result
+    }
+
+    /// Send a PINGREQ if nothing has been sent for the keep alive interval.
+    ///
+    /// [`run()`](#method.run) does this while waiting. Call it regularly
+    /// when publishing less often than the keep alive interval in your own
+    /// loop.
+    pub fn ping_if_idle(&mut self) -> io::Result<()> {
+        match self.config.keep_alive() {
+            Some(keep_alive) if self.last_sent.elapsed() >= keep_alive => self.ping(),
+            _ => Ok(()),
+        }
+    }
+
+    fn send(&mut self, packet: &[u8]) -> io::Result<()> {
+        let stream = self
+            .stream
+            .as_mut()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::NotConnected))?;
+        let result = stream.write_all(packet);
+        match result {
+            Ok(()) => self.last_sent = Instant::now(),
+            Err(_) => self.stream = None,
+        }
+        result
+    }
+
+    /// Wait until `deadline`, sending a PINGREQ whenever the connection has
+    /// been idle for the keep alive interval.
+    fn wait_until(&mut self, deadline: Instant) -> io::Result<()> {
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(());
+            }
+            let wake_up = match self.config.keep_alive() {
+                Some(keep_alive) => {
+                    let ping_at = self.last_sent + keep_alive;
+                    if ping_at <= now {
+                        self.ping()?;
+                        continue;
+                    }
+                    ping_at.min(deadline)
+                }
+                None => deadline,
+            };
+            thread::sleep(wake_up - now);
+        }
+    }
+
+    fn publish_value(&mut self, key: &str, value: &str) -> io::Result<()> {
+        let topic = format!("{}/{}", self.config.base_topic, key);
+        self.publish_message(&topic, value.as_bytes(), false)
+    }
+}
+
+impl<I2C, E> Publisher<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    /// Read a measurement and publish its values.
+    ///
+    /// The color temperature is not published if it cannot be computed.
+    pub fn publish(&mut self) -> Result<(), MqttError<E>> {
+        let m = self.sensor.read()?;
+        let lux = m.lux(self.sensor.range());
+        self.publish_value("red", &m.red.to_string())?;
+        self.publish_value("green", &m.green.to_string())?;
+        self.publish_value("blue", &m.blue.to_string())?;
+        self.publish_value("illuminance", &format!("{:.1}", lux))?;
+        if let Some(cct) = Xyz::from_measurement(&m).cct() {
+            self.publish_value("color_temperature", &format!("{:.0}", cct))?;
+        }
+        Ok(())
+    }
+
+    /// Publish the values every `period` forever.
+    ///
+    /// The values are published at fixed intervals from the start. If
+    /// publishing takes longer than the period, the next values are published
+    /// immediately and the schedule continues from there. While waiting, a
+    /// PINGREQ is sent whenever nothing has been sent for the keep alive
+    /// interval, so the period may be longer than that. Stops at the first
+    /// error.
+    pub fn run(&mut self, period: Duration) -> Result<(), MqttError<E>> {
+        let mut next = Instant::now();
+        loop {
+            self.publish()?;
+            next += period;
+            let now = Instant::now();
+            if next < now {
+                next = now;
+            }
+            self.wait_until(next)?;
+        }
+    }
+}
+
+fn push_length(packet: &mut Vec<u8>, mut length: usize) {
+    loop {
+        let mut byte = (length % 128) as u8;
+        length /= 128;
+        if length > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if length == 0 {
+            break;
+        }
+    }
+}
+
+fn push_string(packet: &mut Vec<u8>, value: &[u8]) {
+    packet.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    packet.extend_from_slice(value);
+}
+
+fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![header];
+    push_length(&mut packet, body.len());
+    packet.extend_from_slice(body);
+    packet
+}
+
+fn connect_packet(config: &MqttConfig) -> Vec<u8> {
+    let mut flags = 0x02; // Clean session
+    if config.username.is_some() {
+        flags |= 0x80;
+    }
+    if config.password.is_some() {
+        flags |= 0x40;
+    }
+    let mut body = Vec::new();
+    push_string(&mut body, b"MQTT");
+    body.push(4); // Protocol level 3.1.1
+    body.push(flags);
+    body.extend_from_slice(&config.keep_alive_s.to_be_bytes());
+    push_string(&mut body, config.client_id.as_bytes());
+    if let Some(username) = &config.username {
+        push_string(&mut body, username.as_bytes());
+    }
+    if let Some(password) = &config.password {
+        push_string(&mut body, password.as_bytes());
+    }
+    packet(0x10, &body)
+}
+
+fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    push_string(&mut body, topic.as_bytes());
+    body.extend_from_slice(payload);
+    packet(0x30 | u8::from(retain), &body)
+}
+
+fn json_string(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn discovery_message(config: &MqttConfig, entity: &Entity) -> (String, String) {
+    let topic = format!(
+        "{}/sensor/{}/{}/config",
+        config.discovery_prefix, config.client_id, entity.key
+    );
+    let mut payload = format!(
+        "{{\"name\":{},\"unique_id\":{},\"state_topic\":{},\"state_class\":\"measurement\"",
+        json_string(entity.name),
+        json_string(&format!("{}_{}", config.client_id, entity.key)),
+        json_string(&format!("{}/{}", config.base_topic, entity.key)),
+    );
+    if let Some(unit) = entity.unit {
+        payload.push_str(&format!(",\"unit_of_measurement\":{}", json_string(unit)));
+    }
+    if let Some(device_class) = entity.device_class {
+        payload.push_str(&format!(",\"device_class\":{}", json_string(device_class)));
+    }
+    payload.push_str(&format!(
+        ",\"device\":{{\"identifiers\":[{}],\"name\":{},\"model\":\"ISL29125\",\
+         \"manufacturer\":\"Renesas\"}}}}",
+        json_string(&config.client_id),
+        json_string(&config.device_name),
+    ));
+    (topic, payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_remaining_length() {
+        let encode = |length| {
+            let mut packet = Vec::new();
+            push_length(&mut packet, length);
+            packet
+        };
+        assert_eq!(vec![0], encode(0));
+        assert_eq!(vec![127], encode(127));
+        assert_eq!(vec![0x80, 1], encode(128));
+        assert_eq!(vec![0xFF, 0x7F], encode(16_383));
+        assert_eq!(vec![0x80, 0x80, 1], encode(16_384));
+    }
+
+    #[test]
+    fn encodes_connect_with_credentials() {
+        let config = MqttConfig::new("localhost", "id")
+            .with_credentials("u", "p")
+            .with_keep_alive(10);
+        assert_eq!(
+            vec![
+                0x10, 20, 0, 4, b'M', b'Q', b'T', b'T', 4, 0xC2, 0, 10, 0, 2, b'i', b'd', 0, 1,
+                b'u', 0, 1, b'p'
+            ],
+            connect_packet(&config)
+        );
+    }
+
+    #[test]
+    fn encodes_retained_publish() {
+        assert_eq!(
+            vec![0x31, 5, 0, 1, b't', b'4', b'2'],
+            publish_packet("t", b"42", true)
+        );
+    }
+
+    #[test]
+    fn does_not_show_password() {
+        let config = MqttConfig::new("localhost", "id").with_credentials("user", "secret");
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("\"user\""));
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("secret"));
+    }
+
+    #[test]
+    fn escapes_json_strings() {
+        assert_eq!("\"a\\\"b\\\\c\\u000a\"", json_string("a\"b\\c\n"));
+    }
+}
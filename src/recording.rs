@@ -0,0 +1,523 @@
+//! Recording and replay of I²C traffic.
+//!
+//! Requires the `std` feature.
+//!
+//! A [`Recorder`] wraps any I²C implementation and logs every transaction
+//! with its data, including the responses and failures. A [`Replayer`] is an
+//! I²C implementation that answers with the recorded responses and flags any
+//! divergence from the recorded transaction sequence. This allows capturing
+//! the traffic on a board and reproducing it in a regression test.
+//!
+//! The log has one transaction per line with hexadecimal values:
+//!
+//! ```text
+//! # Comments and empty lines are ignored
+//! W 44 0105
+//! WR 44 09 d20416092e16
+//! WR 44 08 error 1
+//! ```
+//!
+//! `W` lines contain the address and the bytes written. `WR` lines contain the
+//! address, the bytes written and the bytes read. A failed transaction ends
+//! with `error` and, for `WR`, the number of bytes that were to be read.
+//!
+//! Recording:
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{recording::Recorder, Isl29125, OperatingMode};
+//! use std::fs::File;
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let log = File::create("isl29125.i2c").unwrap();
+//! let mut sensor = Isl29125::new(Recorder::new(dev, log));
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let m = sensor.read().unwrap();
+//! # }
+//! ```
+//!
+//! Replay:
+//!
+//! ```no_run
+//! use isl29125::{recording::Replayer, Isl29125, OperatingMode};
+//!
+//! # fn main() {
+//! let replayer = Replayer::from_file("isl29125.i2c").unwrap();
+//! let mut sensor = Isl29125::new(replayer);
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let m = sensor.read().unwrap();
+//! let replayer = sensor.destroy();
+//! assert!(replayer.is_finished());
+//! # }
+//! ```
+//!
+//! [`Recorder`]: struct.Recorder.html
+//! [`Replayer`]: struct.Replayer.html
+
+use embedded_hal::blocking::i2c;
+use std::{
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+/// Recorded I²C transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Transaction {
+    /// Write
+    Write {
+        /// Device address
+        address: u8,
+        /// Bytes written
+        bytes: Vec<u8>,
+        /// Whether the transaction failed
+        failed: bool,
+    },
+    /// Write followed by a read
+    WriteRead {
+        /// Device address
+        address: u8,
+        /// Bytes written
+        bytes: Vec<u8>,
+        /// Bytes read
+        ///
+        /// If the transaction failed, this is zero-filled to the length of
+        /// the read.
+        response: Vec<u8>,
+        /// Whether the transaction failed
+        failed: bool,
+    },
+}
+
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    if bytes.is_empty() {
+        return write!(f, "-");
+    }
+    for byte in bytes {
+        write!(f, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+    if value == "-" {
+        return Some(Vec::new());
+    }
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match std::str::from_utf8(pair) {
+            Ok(pair) if pair.len() == 2 => u8::from_str_radix(pair, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Transaction::Write {
+                address,
+                bytes,
+                failed,
+            } => {
+                write!(f, "W {:02x} ", address)?;
+                write_hex(f, bytes)?;
+                if *failed {
+                    write!(f, " error")?;
+                }
+            }
+            Transaction::WriteRead {
+                address,
+                bytes,
+                response,
+                failed,
+            } => {
+                write!(f, "WR {:02x} ", address)?;
+                write_hex(f, bytes)?;
+                if *failed {
+                    write!(f, " error {}", response.len())?;
+                } else {
+                    write!(f, " ")?;
+                    write_hex(f, response)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Transaction {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next()?;
+        let address = u8::from_str_radix(fields.next()?, 16).ok()?;
+        let bytes = parse_hex(fields.next()?)?;
+        let transaction = match (kind, fields.next(), fields.next()) {
+            ("W", None, _) => Transaction::Write {
+                address,
+                bytes,
+                failed: false,
+            },
+            ("W", Some("error"), None) => Transaction::Write {
+                address,
+                bytes,
+                failed: true,
+            },
+            ("WR", Some("error"), Some(len)) => Transaction::WriteRead {
+                address,
+                bytes,
+                response: vec![0; len.parse().ok()?],
+                failed: true,
+            },
+            ("WR", Some(response), None) => Transaction::WriteRead {
+                address,
+                bytes,
+                response: parse_hex(response)?,
+                failed: false,
+            },
+            _ => return None,
+        };
+        match fields.next() {
+            None => Some(transaction),
+            Some(_) => None,
+        }
+    }
+
+    /// Whether `self` is the recording of the request `other`, ignoring the
+    /// data read and the result.
+    fn matches(&self, other: &Transaction) -> bool {
+        match (self, other) {
+            (
+                Transaction::Write { address, bytes, .. },
+                Transaction::Write {
+                    address: a,
+                    bytes: b,
+                    ..
+                },
+            ) => address == a && bytes == b,
+            (
+                Transaction::WriteRead {
+                    address,
+                    bytes,
+                    response,
+                    ..
+                },
+                Transaction::WriteRead {
+                    address: a,
+                    bytes: b,
+                    response: r,
+                    ..
+                },
+            ) => address == a && bytes == b && response.len() == r.len(),
+            _ => false,
+        }
+    }
+}
+
+/// I²C wrapper logging all transactions
+///
+/// Logging errors do not affect the I²C transactions. The first one is kept
+/// and can be retrieved with [`io_error()`](#method.io_error).
+#[derive(Debug)]
+pub struct Recorder<I2C, W> {
+    i2c: I2C,
+    log: W,
+    io_error: Option<io::Error>,
+}
+
+impl<I2C, W: Write> Recorder<I2C, W> {
+    /// Wrap an I²C implementation, logging into `log`.
+    pub fn new(i2c: I2C, log: W) -> Self {
+        Recorder {
+            i2c,
+            log,
+            io_error: None,
+        }
+    }
+
+    /// Return the I²C implementation and the log.
+    pub fn into_inner(self) -> (I2C, W) {
+        (self.i2c, self.log)
+    }
+
+    /// First error that occurred while logging, if any
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.io_error.as_ref()
+    }
+
+    fn record(&mut self, transaction: &Transaction) {
+        if let Err(e) = writeln!(self.log, "{}", transaction).and_then(|_| self.log.flush()) {
+            self.io_error.get_or_insert(e);
+        }
+    }
+}
+
+impl<I2C: i2c::Write, W: Write> i2c::Write for Recorder<I2C, W> {
+    type Error = I2C::Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        let result = self.i2c.write(address, bytes);
+        self.record(&Transaction::Write {
+            address,
+            bytes: bytes.to_vec(),
+            failed: result.is_err(),
+        });
+        result
+    }
+}
+
+impl<I2C: i2c::WriteRead, W: Write> i2c::WriteRead for Recorder<I2C, W> {
+    type Error = I2C::Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let result = self.i2c.write_read(address, bytes, buffer);
+        let response = match result {
+            Ok(()) => buffer.to_vec(),
+            Err(_) => vec![0; buffer.len()],
+        };
+        self.record(&Transaction::WriteRead {
+            address,
+            bytes: bytes.to_vec(),
+            response,
+            failed: result.is_err(),
+        });
+        result
+    }
+}
+
+/// Replay error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The transaction failed in the recording
+    Recorded,
+    /// The transaction does not match the recording
+    Diverged(Divergence),
+}
+
+/// Difference between the recorded and the actual transaction sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    /// Position of the transaction in the sequence (starting at 0)
+    pub index: usize,
+    /// Recorded transaction, or `None` if the recording had ended
+    pub expected: Option<Transaction>,
+    /// Requested transaction
+    ///
+    /// For reads, the response is zero-filled to the requested length.
+    pub actual: Transaction,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "transaction {}: got `{}`, expected ",
+            self.index, self.actual
+        )?;
+        match &self.expected {
+            Some(expected) => write!(f, "`{}`", expected),
+            None => write!(f, "end of recording"),
+        }
+    }
+}
+
+/// Parse error with the number of the offending line (starting at 1)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseError {
+    /// Line number
+    pub line: usize,
+}
+
+/// I²C implementation replaying a recording
+///
+/// Once a divergence has been detected, all subsequent transactions fail
+/// with the same error.
+#[derive(Debug, Clone)]
+pub struct Replayer {
+    transactions: Vec<Transaction>,
+    position: usize,
+    divergence: Option<Divergence>,
+}
+
+impl Replayer {
+    /// Create a replayer from a sequence of transactions.
+    pub fn new(transactions: Vec<Transaction>) -> Self {
+        Replayer {
+            transactions,
+            position: 0,
+            divergence: None,
+        }
+    }
+
+    /// Parse a recording.
+    pub fn parse(log: &str) -> Result<Self, ParseError> {
+        let transactions = log
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(i, line)| Transaction::parse(line).ok_or(ParseError { line: i + 1 }))
+            .collect::<Result<_, _>>()?;
+        Ok(Self::new(transactions))
+    }
+
+    /// Read and parse a recording file.
+    ///
+    /// Parse errors are returned as `io::ErrorKind::InvalidData`.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let log = fs::read_to_string(path)?;
+        Self::parse(&log).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid transaction in line {}", e.line),
+            )
+        })
+    }
+
+    /// Whether all recorded transactions have been replayed without
+    /// divergence
+    pub fn is_finished(&self) -> bool {
+        self.divergence.is_none() && self.position == self.transactions.len()
+    }
+
+    /// Number of transactions replayed so far
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// First divergence detected, if any
+    pub fn divergence(&self) -> Option<&Divergence> {
+        self.divergence.as_ref()
+    }
+
+    fn replay(&mut self, actual: Transaction) -> Result<&Transaction, ReplayError> {
+        if let Some(divergence) = &self.divergence {
+            return Err(ReplayError::Diverged(divergence.clone()));
+        }
+        let expected = self.transactions.get(self.position);
+        match expected {
+            Some(expected) if expected.matches(&actual) => {
+                self.position += 1;
+                match expected {
+                    Transaction::Write { failed: true, .. }
+                    | Transaction::WriteRead { failed: true, .. } => Err(ReplayError::Recorded),
+                    _ => Ok(expected),
+                }
+            }
+            _ => {
+                let divergence = Divergence {
+                    index: self.position,
+                    expected: expected.cloned(),
+                    actual,
+                };
+                self.divergence = Some(divergence.clone());
+                Err(ReplayError::Diverged(divergence))
+            }
+        }
+    }
+}
+
+impl i2c::Write for Replayer {
+    type Error = ReplayError;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.replay(Transaction::Write {
+            address,
+            bytes: bytes.to_vec(),
+            failed: false,
+        })
+        .map(|_| ())
+    }
+}
+
+impl i2c::WriteRead for Replayer {
+    type Error = ReplayError;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        let recorded = self.replay(Transaction::WriteRead {
+            address,
+            bytes: bytes.to_vec(),
+            response: vec![0; buffer.len()],
+            failed: false,
+        })?;
+        if let Transaction::WriteRead { response, .. } = recorded {
+            buffer.copy_from_slice(response);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transactions_round_trip() {
+        let transactions = [
+            Transaction::Write {
+                address: 0x44,
+                bytes: vec![1, 5],
+                failed: false,
+            },
+            Transaction::Write {
+                address: 0x44,
+                bytes: vec![],
+                failed: true,
+            },
+            Transaction::WriteRead {
+                address: 0x44,
+                bytes: vec![9],
+                response: vec![0xd2, 0x04],
+                failed: false,
+            },
+            Transaction::WriteRead {
+                address: 0x44,
+                bytes: vec![8],
+                response: vec![0],
+                failed: true,
+            },
+        ];
+        let lines = [
+            "W 44 0105",
+            "W 44 - error",
+            "WR 44 09 d204",
+            "WR 44 08 error 1",
+        ];
+        for (transaction, line) in transactions.iter().zip(lines.iter()) {
+            assert_eq!(*line, transaction.to_string());
+            assert_eq!(Some(transaction.clone()), Transaction::parse(line));
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        for line in [
+            "X 44 01",
+            "W 44",
+            "W 444 01",
+            "W 44 012",
+            "W 44 01 fail",
+            "WR 44 09",
+            "WR 44 09 error",
+            "WR 44 09 0102 03",
+        ] {
+            assert_eq!(None, Transaction::parse(line), "{}", line);
+        }
+    }
+}
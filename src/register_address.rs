@@ -14,6 +14,22 @@ impl Register {
     pub const GREEN_L: u8 = 0x09;
     pub const RED_L: u8 = 0x0B;
     pub const BLUE_L: u8 = 0x0D;
+
+    #[cfg(feature = "defmt")]
+    pub fn name(register: u8) -> &'static str {
+        match register {
+            Register::DEVICE_ID => "DEVICE_ID",
+            Register::CONFIG1 => "CONFIG1",
+            Register::CONFIG2 => "CONFIG2",
+            Register::CONFIG3 => "CONFIG3",
+            Register::THL => "THL",
+            Register::STATUS => "STATUS",
+            Register::GREEN_L => "GREEN_L",
+            Register::RED_L => "RED_L",
+            Register::BLUE_L => "BLUE_L",
+            _ => "UNKNOWN",
+        }
+    }
 }
 
 pub struct BitFlags;
@@ -28,12 +44,21 @@ impl BitFlags {
     pub const RGBTHF: u8 = 1;
 }
 
+// Every register access of the driver goes through the methods below, so the
+// `defmt` tracing lives here instead of in each method in `device_impl`.
 impl<I2C, E> Isl29125<I2C>
 where
     I2C: i2c::Write<Error = E>,
 {
     pub(crate) fn write_register(&mut self, register: u8, data: u8) -> Result<(), Error<E>> {
         let payload: [u8; 2] = [register, data];
+        #[cfg(feature = "defmt")]
+        defmt::trace!(
+            "write {=str} (0x{=u8:02x}) <- 0x{=u8:02x}",
+            Register::name(register),
+            register,
+            data
+        );
         self.i2c.write(ADDR, &payload).map_err(Error::I2C)
     }
 
@@ -45,6 +70,8 @@ where
             (high & 0xFF) as u8,
             ((high & 0xFF00) >> 8) as u8,
         ];
+        #[cfg(feature = "defmt")]
+        defmt::trace!("write THL..THH <- low: {=u16}, high: {=u16}", low, high);
         self.i2c.write(ADDR, &payload).map_err(Error::I2C)
     }
 }
@@ -67,6 +94,14 @@ where
     pub(crate) fn read_data(&mut self, register: u8, data: &mut [u8]) -> Result<(), Error<E>> {
         self.i2c
             .write_read(ADDR, &[register], data)
-            .map_err(Error::I2C)
+            .map_err(Error::I2C)?;
+        #[cfg(feature = "defmt")]
+        defmt::trace!(
+            "read {=str} (0x{=u8:02x}) -> {=[u8]:02x}",
+            Register::name(register),
+            register,
+            data
+        );
+        Ok(())
     }
 }
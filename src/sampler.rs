@@ -0,0 +1,403 @@
+//! Continuous sampling with rolling statistics.
+//!
+//! A [`Sampler`] keeps the last `N` timestamped measurements in a
+//! fixed-capacity ring buffer and provides per-channel statistics over them.
+//! It does not allocate and `Sampler::new()` is a `const fn`, so it can be
+//! placed in a `static` shared with an interrupt handler.
+//!
+//! The samples are stored as they are pushed, but the statistics are always
+//! computed at 16-bit resolution so that they remain meaningful if the ADC
+//! resolution changes while the window contains samples taken at another
+//! resolution. Each sample is rescaled with
+//! [`Measurement::to_resolution()`] before being accumulated.
+//!
+//! [`Sampler`]: struct.Sampler.html
+//! [`Measurement::to_resolution()`]: ../struct.Measurement.html#method.to_resolution
+
+use crate::{Error, Isl29125, Measurement, Resolution};
+use embedded_hal::blocking::i2c;
+
+/// Measurement together with the time at which it was taken
+///
+/// The timestamp unit is defined by the application (e.g. timer ticks or
+/// milliseconds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sample {
+    /// Time at which the measurement was taken
+    pub timestamp: u64,
+    /// Measurement
+    pub measurement: Measurement,
+}
+
+/// Statistics of a single color channel in 16-bit resolution counts
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelStatistics {
+    /// Mean value (rounded)
+    pub mean: u16,
+    /// Minimum value
+    pub min: u16,
+    /// Maximum value
+    pub max: u16,
+    /// Population standard deviation (rounded down)
+    pub std_dev: u16,
+}
+
+/// Statistics of all color channels
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Statistics {
+    /// Red
+    pub red: ChannelStatistics,
+    /// Green
+    pub green: ChannelStatistics,
+    /// Blue
+    pub blue: ChannelStatistics,
+}
+
+const EMPTY_SAMPLE: Sample = Sample {
+    timestamp: 0,
+    measurement: Measurement::new(0, 0, 0, Resolution::Bit16),
+};
+
+/// Fixed-capacity ring buffer of the last `N` samples
+///
+/// Once full, adding a new sample replaces the oldest one. The sums used for
+/// the mean and standard deviation are updated on every push so that adding
+/// a sample takes constant time. This makes it suitable for being fed from an
+/// interrupt handler.
+#[derive(Debug, Clone)]
+pub struct Sampler<const N: usize> {
+    samples: [Sample; N],
+    start: usize,
+    len: usize,
+    sums: [u64; 3],
+    square_sums: [u64; 3],
+}
+
+impl<const N: usize> Default for Sampler<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Sampler<N> {
+    /// Create an empty sampler.
+    pub const fn new() -> Self {
+        Sampler {
+            samples: [EMPTY_SAMPLE; N],
+            start: 0,
+            len: 0,
+            sums: [0; 3],
+            square_sums: [0; 3],
+        }
+    }
+
+    /// Read all colors from the sensor and add them as a new sample.
+    ///
+    /// The measurement is returned as well.
+    pub fn sample<I2C, E>(
+        &mut self,
+        sensor: &mut Isl29125<I2C>,
+        timestamp: u64,
+    ) -> Result<Measurement, Error<E>>
+    where
+        I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    {
+        let measurement = sensor.read()?;
+        self.push(timestamp, measurement);
+        Ok(measurement)
+    }
+
+    /// Add a new sample, replacing the oldest one if the sampler is full.
+    pub fn push(&mut self, timestamp: u64, measurement: Measurement) {
+        if N == 0 {
+            return;
+        }
+        if self.len == N {
+            let oldest = self.samples[self.start].measurement;
+            self.update_sums(&oldest, |sum, value| sum - value);
+            self.start = (self.start + 1) % N;
+            self.len -= 1;
+        }
+        let index = (self.start + self.len) % N;
+        self.samples[index] = Sample {
+            timestamp,
+            measurement,
+        };
+        self.len += 1;
+        self.update_sums(&measurement, |sum, value| sum + value);
+    }
+
+    fn update_sums(&mut self, m: &Measurement, op: impl Fn(u64, u64) -> u64) {
+        for (i, value) in channels_16bit(m).iter().enumerate() {
+            let value = u64::from(*value);
+            self.sums[i] = op(self.sums[i], value);
+            self.square_sums[i] = op(self.square_sums[i], value * value);
+        }
+    }
+
+    /// Remove all samples.
+    pub fn clear(&mut self) {
+        self.start = 0;
+        self.len = 0;
+        self.sums = [0; 3];
+        self.square_sums = [0; 3];
+    }
+
+    /// Number of samples currently stored
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no samples are stored
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Whether the sampler is full (adding more samples replaces the oldest ones)
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Maximum number of samples stored
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Most recent sample
+    pub fn latest(&self) -> Option<&Sample> {
+        self.iter().next_back()
+    }
+
+    /// Iterate over the stored samples from the oldest to the most recent one.
+    ///
+    /// Use `.rev()` to iterate from the most recent one.
+    pub fn iter(&self) -> Iter<'_, N> {
+        Iter {
+            sampler: self,
+            front: 0,
+            back: self.len,
+        }
+    }
+
+    /// Statistics per channel over all stored samples
+    ///
+    /// The values are given at 16-bit resolution regardless of the resolution
+    /// of the samples.
+    /// Returns `None` if no samples are stored. The mean and standard
+    /// deviation are computed from running sums while the minimum and
+    /// maximum require going through all samples.
+    pub fn statistics(&self) -> Option<Statistics> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut stats = [ChannelStatistics {
+            min: u16::MAX,
+            ..ChannelStatistics::default()
+        }; 3];
+        for sample in self.iter() {
+            let values = channels_16bit(&sample.measurement);
+            for (stat, value) in stats.iter_mut().zip(values.iter()) {
+                stat.min = stat.min.min(*value);
+                stat.max = stat.max.max(*value);
+            }
+        }
+        let n = self.len as u64;
+        for (i, stat) in stats.iter_mut().enumerate() {
+            stat.mean = ((self.sums[i] + n / 2) / n) as u16;
+            // n² · variance = n · Σx² - (Σx)²
+            let sum = u128::from(self.sums[i]);
+            let n_variance = u128::from(n) * u128::from(self.square_sums[i]) - sum * sum;
+            let variance = n_variance / (u128::from(n) * u128::from(n));
+            stat.std_dev = isqrt(variance as u64) as u16;
+        }
+        Some(Statistics {
+            red: stats[0],
+            green: stats[1],
+            blue: stats[2],
+        })
+    }
+}
+
+impl<'a, const N: usize> IntoIterator for &'a Sampler<N> {
+    type Item = &'a Sample;
+    type IntoIter = Iter<'a, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// Iterator over the samples stored in a [`Sampler`](struct.Sampler.html)
+#[derive(Debug, Clone)]
+pub struct Iter<'a, const N: usize> {
+    sampler: &'a Sampler<N>,
+    front: usize,
+    back: usize,
+}
+
+impl<'a, const N: usize> Iter<'a, N> {
+    fn get(&self, index: usize) -> &'a Sample {
+        &self.sampler.samples[(self.sampler.start + index) % N]
+    }
+}
+
+impl<'a, const N: usize> Iterator for Iter<'a, N> {
+    type Item = &'a Sample;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        let sample = self.get(self.front);
+        self.front += 1;
+        Some(sample)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, const N: usize> DoubleEndedIterator for Iter<'a, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(self.get(self.back))
+    }
+}
+
+impl<'a, const N: usize> ExactSizeIterator for Iter<'a, N> {}
+
+fn channels_16bit(m: &Measurement) -> [u16; 3] {
+    let m = m.to_resolution(Resolution::Bit16);
+    [m.red, m.green, m.blue]
+}
+
+/// Integer square root (rounded down)
+fn isqrt(value: u64) -> u64 {
+    if value < 2 {
+        return value;
+    }
+    // Newton's method starting from a value guaranteed to be above the root.
+    let mut x = 1u64 << (32 - value.leading_zeros() / 2);
+    loop {
+        let y = (x + value / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(red: u16, green: u16, blue: u16) -> Measurement {
+        Measurement::new(red, green, blue, Resolution::Bit16)
+    }
+
+    #[test]
+    fn new_sampler_is_empty() {
+        let sampler = Sampler::<4>::new();
+        assert!(sampler.is_empty());
+        assert_eq!(4, sampler.capacity());
+        assert_eq!(None, sampler.statistics());
+        assert_eq!(None, sampler.latest());
+    }
+
+    #[test]
+    fn replaces_oldest_sample_when_full() {
+        let mut sampler = Sampler::<3>::new();
+        for t in 0..5 {
+            sampler.push(t, m(t as u16, 0, 0));
+        }
+        assert!(sampler.is_full());
+        let timestamps: [u64; 3] = [2, 3, 4];
+        assert!(sampler
+            .iter()
+            .map(|s| s.timestamp)
+            .eq(timestamps.iter().cloned()));
+        assert!(sampler
+            .iter()
+            .rev()
+            .map(|s| s.timestamp)
+            .eq(timestamps.iter().rev().cloned()));
+        assert_eq!(4, sampler.latest().unwrap().timestamp);
+    }
+
+    #[test]
+    fn computes_statistics() {
+        let mut sampler = Sampler::<8>::new();
+        for (t, v) in [2, 4, 4, 4, 5, 5, 7, 9].iter().enumerate() {
+            sampler.push(t as u64, m(*v, 100, 65535));
+        }
+        let stats = sampler.statistics().unwrap();
+        let expected = ChannelStatistics {
+            mean: 5,
+            min: 2,
+            max: 9,
+            std_dev: 2,
+        };
+        assert_eq!(expected, stats.red);
+        assert_eq!(100, stats.green.mean);
+        assert_eq!(0, stats.green.std_dev);
+        assert_eq!(65535, stats.blue.mean);
+        assert_eq!(65535, stats.blue.max);
+    }
+
+    #[test]
+    fn statistics_follow_window() {
+        let mut sampler = Sampler::<2>::new();
+        sampler.push(0, m(1000, 0, 0));
+        sampler.push(1, m(10, 0, 0));
+        sampler.push(2, m(20, 0, 0));
+        let stats = sampler.statistics().unwrap();
+        assert_eq!(15, stats.red.mean);
+        assert_eq!(10, stats.red.min);
+        assert_eq!(20, stats.red.max);
+        assert_eq!(5, stats.red.std_dev);
+    }
+
+    #[test]
+    fn computes_statistics_at_16bit_resolution() {
+        let mut sampler = Sampler::<2>::new();
+        sampler.push(0, m(0, 0, 0));
+        sampler.push(1, Measurement::new(4095, 0, 0, Resolution::Bit12));
+        let stats = sampler.statistics().unwrap();
+        // The maximum count at 12-bit resolution is 65535 at 16-bit resolution.
+        assert_eq!(32768, stats.red.mean);
+        assert_eq!(0, stats.red.min);
+        assert_eq!(65535, stats.red.max);
+        assert_eq!(32767, stats.red.std_dev);
+        sampler.push(2, m(65535, 0, 0));
+        let stats = sampler.statistics().unwrap();
+        assert_eq!(65535, stats.red.mean);
+        assert_eq!(0, stats.red.std_dev);
+    }
+
+    #[test]
+    fn can_clear() {
+        let mut sampler = Sampler::<2>::new();
+        sampler.push(0, m(1, 2, 3));
+        sampler.clear();
+        assert!(sampler.is_empty());
+        assert_eq!(0, sampler.iter().count());
+    }
+
+    #[test]
+    fn computes_integer_square_root() {
+        for value in 0..10_000u64 {
+            let root = isqrt(value);
+            assert!(root * root <= value);
+            assert!((root + 1) * (root + 1) > value);
+        }
+        assert_eq!(u64::from(u32::MAX), isqrt(u64::MAX));
+    }
+}
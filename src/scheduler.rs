@@ -0,0 +1,143 @@
+//! Duty-cycled low-power measurements.
+//!
+//! The sensor draws 56µA while converting and 0.5µA in power-down mode.
+//! A [`Scheduler`] wakes the sensor up only for as long as it takes to
+//! convert all enabled channels and powers it down again afterwards.
+//!
+//! [`Scheduler`]: struct.Scheduler.html
+
+use crate::{Error, InterruptPinMode, Isl29125, Measurement, OperatingMode, Resolution};
+use embedded_hal::blocking::{delay::DelayUs, i2c};
+
+/// Periodic measurement scheduler
+///
+/// For each sample the sensor is set to the configured operating mode, the
+/// conversion time of all enabled channels at the current resolution is
+/// waited and the colors are read. Then the sensor is set to
+/// `OperatingMode::PowerDown` again.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Scheduler {
+    mode: OperatingMode,
+    period_us: u32,
+}
+
+impl Scheduler {
+    /// Create a new scheduler taking a sample every `period_us` microseconds
+    /// in the given operating mode.
+    pub const fn new(mode: OperatingMode, period_us: u32) -> Self {
+        Scheduler { mode, period_us }
+    }
+
+    /// Operating mode used during the measurements
+    pub const fn operating_mode(&self) -> OperatingMode {
+        self.mode
+    }
+
+    /// Sampling period in microseconds
+    pub const fn period_us(&self) -> u32 {
+        self.period_us
+    }
+
+    /// Time the sensor is active for each sample at the given resolution
+    /// in microseconds
+    pub const fn active_time_us(&self, resolution: Resolution) -> u32 {
+        self.mode.cycle_time_us(resolution)
+    }
+
+    /// Estimated average supply current in nanoamperes at the given resolution
+    ///
+    /// This is computed from the typical supply currents in the datasheet.
+    /// If the period is shorter than the active time, the sensor never
+    /// powers down.
+    pub const fn average_current_na(&self, resolution: Resolution) -> u32 {
+        let active = self.active_time_us(resolution) as u64;
+        let period = self.period_us as u64;
+        let active_current = self.mode.supply_current_na() as u64;
+        if period <= active {
+            return active_current as u32;
+        }
+        let idle_current = OperatingMode::PowerDown.supply_current_na() as u64;
+        ((active_current * active + idle_current * (period - active)) / period) as u32
+    }
+
+    /// Take a single measurement and power down the sensor afterwards.
+    ///
+    /// The sensor is powered down even if reading the data fails. In that
+    /// case the read error is returned even if powering down fails as well.
+    ///
+    /// The conversion is started by writing the operating mode, which does
+    /// not start a conversion in `InterruptPinMode::SyncStart`. If the
+    /// interrupt pin is in that mode or no color channel is enabled in the
+    /// operating mode, `Error::InvalidInputData` is returned.
+    pub fn measure<I2C, E, D>(
+        &self,
+        sensor: &mut Isl29125<I2C>,
+        delay: &mut D,
+    ) -> Result<Measurement, Error<E>>
+    where
+        I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+        D: DelayUs<u32>,
+    {
+        let active_time = self.active_time_us(sensor.resolution());
+        if active_time == 0 || sensor.interrupt_pin_mode() == InterruptPinMode::SyncStart {
+            return Err(Error::InvalidInputData);
+        }
+        sensor.set_operating_mode(self.mode)?;
+        delay.delay_us(active_time);
+        let measurement = sensor.read();
+        let powered_down = sensor.set_operating_mode(OperatingMode::PowerDown);
+        let measurement = measurement?;
+        powered_down?;
+        Ok(measurement)
+    }
+
+    /// Take a single measurement and wait until the end of the period.
+    ///
+    /// Calling this in a loop takes a sample every period. See
+    /// [`measure()`](#method.measure).
+    pub fn measure_and_wait<I2C, E, D>(
+        &self,
+        sensor: &mut Isl29125<I2C>,
+        delay: &mut D,
+    ) -> Result<Measurement, Error<E>>
+    where
+        I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+        D: DelayUs<u32>,
+    {
+        let measurement = self.measure(sensor, delay);
+        let active_time = self.active_time_us(sensor.resolution());
+        delay.delay_us(self.period_us.saturating_sub(active_time));
+        measurement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_compute_active_time() {
+        let scheduler = Scheduler::new(OperatingMode::RedGreenBlue, 1_000_000);
+        assert_eq!(300_000, scheduler.active_time_us(Resolution::Bit16));
+        assert_eq!(18_750, scheduler.active_time_us(Resolution::Bit12));
+        let scheduler = Scheduler::new(OperatingMode::GreenOnly, 1_000_000);
+        assert_eq!(100_000, scheduler.active_time_us(Resolution::Bit16));
+    }
+
+    #[test]
+    fn can_compute_average_current() {
+        let scheduler = Scheduler::new(OperatingMode::RedGreenBlue, 1_000_000);
+        // 0.3 * 56µA + 0.7 * 0.5µA
+        assert_eq!(17_150, scheduler.average_current_na(Resolution::Bit16));
+        let scheduler = Scheduler::new(OperatingMode::GreenOnly, 10_000_000);
+        // 0.01 * 56µA + 0.99 * 0.5µA
+        assert_eq!(1_055, scheduler.average_current_na(Resolution::Bit16));
+    }
+
+    #[test]
+    fn average_current_with_short_period_is_active_current() {
+        let scheduler = Scheduler::new(OperatingMode::RedGreenBlue, 100_000);
+        assert_eq!(56_000, scheduler.average_current_na(Resolution::Bit16));
+    }
+}
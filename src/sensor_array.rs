@@ -0,0 +1,143 @@
+//! Arrays of sensors behind a TCA9548A I2C multiplexer.
+//!
+//! The ISL29125 has a fixed I2C address, so several of them can only be used
+//! on the same bus by placing them behind a multiplexer. A [`SensorArray`]
+//! selects the multiplexer channel of the sensor before each transaction.
+//!
+//! [`SensorArray`]: struct.SensorArray.html
+
+use crate::{Error, Isl29125, Measurement, SensorConfig};
+use embedded_hal::blocking::i2c;
+
+/// Default TCA9548A I2C address (A0, A1 and A2 pins low)
+pub const DEFAULT_MUX_ADDRESS: u8 = 0b111_0000;
+
+/// Number of channels of the TCA9548A
+pub const MUX_CHANNEL_COUNT: u8 = 8;
+
+/// I2C bus going through a multiplexer channel
+///
+/// Implements the I2C traits, selecting the multiplexer channel before
+/// forwarding each transaction to the bus.
+#[derive(Debug)]
+pub struct MuxChannel<'a, I2C> {
+    i2c: &'a mut I2C,
+    mux_address: u8,
+    channel: u8,
+}
+
+impl<'a, I2C, E> MuxChannel<'a, I2C>
+where
+    I2C: i2c::Write<Error = E>,
+{
+    fn select(&mut self) -> Result<(), E> {
+        self.i2c.write(self.mux_address, &[1 << self.channel])
+    }
+}
+
+impl<'a, I2C, E> i2c::Write for MuxChannel<'a, I2C>
+where
+    I2C: i2c::Write<Error = E>,
+{
+    type Error = E;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), E> {
+        self.select()?;
+        self.i2c.write(address, bytes)
+    }
+}
+
+impl<'a, I2C, E> i2c::WriteRead for MuxChannel<'a, I2C>
+where
+    I2C: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+{
+    type Error = E;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), E> {
+        self.select()?;
+        self.i2c.write_read(address, bytes, buffer)
+    }
+}
+
+/// Array of `N` ISL29125 sensors behind a TCA9548A multiplexer
+///
+/// Operations on all sensors are done one sensor after the other and
+/// return one result per sensor, so that a failing sensor does not prevent
+/// the rest from being used.
+#[derive(Debug)]
+pub struct SensorArray<I2C, const N: usize> {
+    i2c: I2C,
+    mux_address: u8,
+    channels: [u8; N],
+    sensors: [Isl29125<()>; N],
+}
+
+impl<I2C, E, const N: usize> SensorArray<I2C, N>
+where
+    I2C: i2c::Write<Error = E> + i2c::WriteRead<Error = E>,
+{
+    /// Create a new sensor array.
+    ///
+    /// `channels` contains the multiplexer channel (`[0-7]`) each sensor is
+    /// connected to. Operations on sensors with an invalid channel will
+    /// return `Error::InvalidInputData`.
+    pub fn new(i2c: I2C, mux_address: u8, channels: [u8; N]) -> Self {
+        SensorArray {
+            i2c,
+            mux_address,
+            channels,
+            sensors: core::array::from_fn(|_| Isl29125::new(())),
+        }
+    }
+
+    /// Destroy the sensor array, return I2C bus.
+    pub fn destroy(self) -> I2C {
+        self.i2c
+    }
+
+    /// Number of sensors in the array
+    pub const fn len(&self) -> usize {
+        N
+    }
+
+    /// Whether the array contains no sensors
+    pub const fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Run an operation on the sensor with the given index.
+    ///
+    /// The driver passed to `f` keeps its state (e.g. the cached
+    /// configuration) between calls.
+    /// Returns `Error::InvalidInputData` if the index is out of bounds or the
+    /// sensor channel is invalid.
+    pub fn with_sensor<F, R>(&mut self, index: usize, f: F) -> Result<R, Error<E>>
+    where
+        F: FnOnce(&mut Isl29125<MuxChannel<'_, I2C>>) -> Result<R, Error<E>>,
+    {
+        let channel = match self.channels.get(index) {
+            Some(&channel) if channel < MUX_CHANNEL_COUNT => channel,
+            _ => return Err(Error::InvalidInputData),
+        };
+        let state = core::mem::replace(&mut self.sensors[index], Isl29125::new(()));
+        let mux_channel = MuxChannel {
+            i2c: &mut self.i2c,
+            mux_address: self.mux_address,
+            channel,
+        };
+        let (mut sensor, ()) = state.swap_i2c(mux_channel);
+        let result = f(&mut sensor);
+        self.sensors[index] = sensor.swap_i2c(()).0;
+        result
+    }
+
+    /// Apply the same configuration to all sensors.
+    pub fn configure(&mut self, config: &SensorConfig) -> [Result<(), Error<E>>; N] {
+        core::array::from_fn(|i| self.with_sensor(i, |sensor| sensor.configure(config)))
+    }
+
+    /// Read all colors from all sensors.
+    pub fn read_all(&mut self) -> [Result<Measurement, Error<E>>; N] {
+        core::array::from_fn(|i| self.with_sensor(i, |sensor| sensor.read()))
+    }
+}
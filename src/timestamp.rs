@@ -0,0 +1,152 @@
+//! Timestamped measurements.
+//!
+//! [`Timestamped`] wraps the driver together with a [`Clock`] and tags every
+//! measurement with the time at which it was read as well as the RGB data
+//! sensing range and operating mode in effect.
+//!
+//! Any `FnMut() -> T` closure is a clock, so a monotonic tick counter can be
+//! used directly. With the `std` feature, [`StdClock`] provides
+//! `std::time::Instant` timestamps and [`SystemClock`] milliseconds since
+//! the Unix epoch.
+//!
+//! ```no_run
+//! extern crate linux_embedded_hal as hal;
+//! use isl29125::{timestamp::Timestamped, Isl29125, OperatingMode};
+//!
+//! # fn main() {
+//! let dev = hal::I2cdev::new("/dev/i2c-1").unwrap();
+//! let mut sensor = Isl29125::new(dev);
+//! sensor
+//!     .set_operating_mode(OperatingMode::RedGreenBlue)
+//!     .unwrap();
+//! let mut ticks = 0_u32;
+//! let mut sensor = Timestamped::new(sensor, move || {
+//!     ticks += 1;
+//!     ticks
+//! });
+//! let m = sensor.read().unwrap();
+//! println!("{}: {:?} in {:?}", m.timestamp, m.measurement, m.range);
+//! # }
+//! ```
+//!
+//! [`Timestamped`]: struct.Timestamped.html
+//! [`Clock`]: trait.Clock.html
+//! [`StdClock`]: struct.StdClock.html
+//! [`SystemClock`]: struct.SystemClock.html
+
+use crate::{Error, Isl29125, Measurement, OperatingMode, Range, Resolution};
+use embedded_hal::blocking::i2c;
+
+/// Source of timestamps
+pub trait Clock {
+    /// Timestamp type
+    type Instant: Copy;
+
+    /// Return the current time.
+    fn now(&mut self) -> Self::Instant;
+}
+
+impl<T: Copy, F: FnMut() -> T> Clock for F {
+    type Instant = T;
+
+    fn now(&mut self) -> T {
+        self()
+    }
+}
+
+/// Clock returning `std::time::Instant` timestamps
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct StdClock;
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    type Instant = std::time::Instant;
+
+    fn now(&mut self) -> Self::Instant {
+        std::time::Instant::now()
+    }
+}
+
+/// Clock returning the milliseconds elapsed since the Unix epoch
+///
+/// Suitable for data logs that should be correlated with wall-clock time.
+#[cfg(feature = "std")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SystemClock;
+
+#[cfg(feature = "std")]
+impl Clock for SystemClock {
+    type Instant = u64;
+
+    fn now(&mut self) -> Self::Instant {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+/// Measurement together with the time and configuration it was taken with
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TimestampedMeasurement<T> {
+    /// Time at which the measurement was read
+    pub timestamp: T,
+    /// Measurement
+    pub measurement: Measurement,
+    /// RGB data sensing range in effect
+    pub range: Range,
+    /// Operating mode in effect
+    pub operating_mode: OperatingMode,
+}
+
+impl<T> TimestampedMeasurement<T> {
+    /// ADC resolution in effect
+    pub const fn resolution(&self) -> Resolution {
+        self.measurement.resolution
+    }
+}
+
+/// Driver wrapper producing timestamped measurements
+#[derive(Debug)]
+pub struct Timestamped<I2C, C> {
+    sensor: Isl29125<I2C>,
+    clock: C,
+}
+
+impl<I2C, C> Timestamped<I2C, C> {
+    /// Wrap a driver instance and a clock.
+    pub fn new(sensor: Isl29125<I2C>, clock: C) -> Self {
+        Timestamped { sensor, clock }
+    }
+
+    /// Destroy the wrapper and return the driver and the clock.
+    pub fn destroy(self) -> (Isl29125<I2C>, C) {
+        (self.sensor, self.clock)
+    }
+
+    /// Access the driver, for example to change its configuration.
+    pub fn sensor(&mut self) -> &mut Isl29125<I2C> {
+        &mut self.sensor
+    }
+}
+
+impl<I2C, E, C> Timestamped<I2C, C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+    C: Clock,
+{
+    /// Read the measurement of all colors and timestamp it.
+    ///
+    /// The timestamp is taken right after the data has been read.
+    pub fn read(&mut self) -> Result<TimestampedMeasurement<C::Instant>, Error<E>> {
+        let measurement = self.sensor.read()?;
+        Ok(TimestampedMeasurement {
+            timestamp: self.clock.now(),
+            measurement,
+            range: self.sensor.range(),
+            operating_mode: self.sensor.operating_mode(),
+        })
+    }
+}
@@ -1,14 +1,24 @@
 /// All possible errors in this crate
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error<E> {
     /// I²C communication error
     I2C(E),
     /// Invalid input data provided
     InvalidInputData,
+    /// A color channel is saturated at the highest RGB data sensing range
+    Saturated,
+    /// No conversion completed in the expected time
+    Timeout,
 }
 
 /// Measurement result
-#[derive(Debug, Clone, Copy, PartialEq)]
+///
+/// The color counts are given at the ADC resolution in effect when the
+/// measurement was taken. Measurements taken at different resolutions
+/// compare equal if they are equal once rescaled to 16-bit resolution.
+#[derive(Debug, Default, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Measurement {
     /// Red
     pub red: u16,
@@ -16,10 +26,156 @@ pub struct Measurement {
     pub green: u16,
     /// Blue
     pub blue: u16,
+    /// ADC resolution of the color counts
+    pub resolution: Resolution,
+}
+
+impl Measurement {
+    /// Create a new measurement.
+    pub const fn new(red: u16, green: u16, blue: u16, resolution: Resolution) -> Self {
+        Measurement {
+            red,
+            green,
+            blue,
+            resolution,
+        }
+    }
+
+    /// Rescale the color counts to another ADC resolution.
+    ///
+    /// The maximum count at one resolution corresponds to the maximum count
+    /// at the other. Values are rounded to the nearest count. Values above
+    /// the maximum count for the resolution of the measurement are clamped
+    /// to the maximum count for the new resolution.
+    pub const fn to_resolution(self, resolution: Resolution) -> Self {
+        let from = self.resolution;
+        Measurement {
+            red: rescale(self.red, from, resolution),
+            green: rescale(self.green, from, resolution),
+            blue: rescale(self.blue, from, resolution),
+            resolution,
+        }
+    }
+
+    /// Color counts as a fraction of the maximum count for the resolution
+    ///
+    /// The values are in the range `[0.0-1.0]` and are therefore independent
+    /// of the resolution. Returned in red, green, blue order.
+    pub fn normalized(&self) -> [f32; 3] {
+        let max = f32::from(self.resolution.max_count());
+        [
+            f32::from(self.red) / max,
+            f32::from(self.green) / max,
+            f32::from(self.blue) / max,
+        ]
+    }
+
+    /// Approximate illuminance in lux at the given RGB data sensing range
+    ///
+    /// Computed from the green channel, whose spectral response is close
+    /// to that of the human eye, as a fraction of the full scale of the range.
+    pub fn lux(&self, range: Range) -> f32 {
+        let max = f32::from(self.resolution.max_count());
+        f32::from(self.green) * range.full_scale_lux() as f32 / max
+    }
+}
+
+/// Rescale a count from one ADC resolution to another rounding to the nearest count.
+///
+/// Values above the maximum count of `from` are clamped to the maximum count of `to`.
+pub(crate) const fn rescale(value: u16, from: Resolution, to: Resolution) -> u16 {
+    let from = from.max_count() as u32;
+    let to = to.max_count() as u32;
+    let value = (value as u32 * to + from / 2) / from;
+    if value > to {
+        to as u16
+    } else {
+        value as u16
+    }
+}
+
+impl PartialEq for Measurement {
+    fn eq(&self, other: &Self) -> bool {
+        let a = self.to_resolution(Resolution::Bit16);
+        let b = other.to_resolution(Resolution::Bit16);
+        a.red == b.red && a.green == b.green && a.blue == b.blue
+    }
+}
+
+/// Condition of a color channel reading
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChannelCondition {
+    /// Reading within the measurable range (default)
+    Normal,
+    /// Reading clipped at the maximum count for the resolution
+    Saturated,
+    /// Reading near zero (below 1/256 of the maximum count for the resolution)
+    UnderRange,
+    /// Channel not converted in the operating mode, so the reading is not
+    /// meaningful
+    Disabled,
+}
+
+impl Default for ChannelCondition {
+    fn default() -> Self {
+        ChannelCondition::Normal
+    }
+}
+
+impl ChannelCondition {
+    /// Condition of a color channel reading taken at the given resolution
+    pub const fn of(value: u16, resolution: Resolution) -> Self {
+        let max = resolution.max_count();
+        if value >= max {
+            ChannelCondition::Saturated
+        } else if value <= max >> 8 {
+            ChannelCondition::UnderRange
+        } else {
+            ChannelCondition::Normal
+        }
+    }
+}
+
+/// Measurement together with the condition of each color channel
+///
+/// Returned by [`read_checked()`](struct.Isl29125.html#method.read_checked).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CheckedMeasurement {
+    /// Measurement
+    pub measurement: Measurement,
+    /// Red channel condition
+    pub red: ChannelCondition,
+    /// Green channel condition
+    pub green: ChannelCondition,
+    /// Blue channel condition
+    pub blue: ChannelCondition,
+    /// RGB data sensing range that would suit the current light level better
+    ///
+    /// `None` if the current range is adequate.
+    pub recommended_range: Option<Range>,
+}
+
+impl CheckedMeasurement {
+    /// Whether any color channel is saturated
+    pub fn is_saturated(&self) -> bool {
+        self.conditions().contains(&ChannelCondition::Saturated)
+    }
+
+    /// Whether any color channel is under range
+    pub fn is_under_range(&self) -> bool {
+        self.conditions().contains(&ChannelCondition::UnderRange)
+    }
+
+    fn conditions(&self) -> [ChannelCondition; 3] {
+        [self.red, self.green, self.blue]
+    }
 }
 
 /// Operating mode
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum OperatingMode {
     /// Power-Down (ADC conversion)
     PowerDown,
@@ -45,8 +201,65 @@ impl Default for OperatingMode {
     }
 }
 
+impl OperatingMode {
+    /// Number of color channels converted in this mode
+    pub const fn channel_count(self) -> u8 {
+        match self {
+            OperatingMode::PowerDown | OperatingMode::StandBy => 0,
+            OperatingMode::RedOnly | OperatingMode::GreenOnly | OperatingMode::BlueOnly => 1,
+            OperatingMode::RedGreen | OperatingMode::GreenBlue => 2,
+            OperatingMode::RedGreenBlue => 3,
+        }
+    }
+
+    /// Conversion time of a single color channel in this mode in microseconds
+    ///
+    /// This is `0` if no color channel is converted in this mode.
+    pub const fn conversion_time_us(self, resolution: Resolution) -> u32 {
+        if self.channel_count() == 0 {
+            0
+        } else {
+            resolution.conversion_time_us()
+        }
+    }
+
+    /// Whether the red, green and blue channels are converted in this mode
+    pub(crate) const fn enabled_channels(self) -> [bool; 3] {
+        match self {
+            OperatingMode::PowerDown | OperatingMode::StandBy => [false, false, false],
+            OperatingMode::RedOnly => [true, false, false],
+            OperatingMode::GreenOnly => [false, true, false],
+            OperatingMode::BlueOnly => [false, false, true],
+            OperatingMode::RedGreen => [true, true, false],
+            OperatingMode::GreenBlue => [false, true, true],
+            OperatingMode::RedGreenBlue => [true, true, true],
+        }
+    }
+
+    /// Time to convert all color channels enabled in this mode in microseconds
+    ///
+    /// The channels are converted one after the other, so this is the time
+    /// it takes for new data to be available in all of them after starting
+    /// a conversion. This is `0` if no color channel is converted in this mode.
+    pub const fn cycle_time_us(self, resolution: Resolution) -> u32 {
+        self.channel_count() as u32 * resolution.conversion_time_us()
+    }
+
+    /// Typical supply current in this mode in nanoamperes
+    ///
+    /// The datasheet specifies a supply current of 56µA while converting and
+    /// 0.5µA otherwise.
+    pub const fn supply_current_na(self) -> u32 {
+        match self {
+            OperatingMode::PowerDown | OperatingMode::StandBy => 500,
+            _ => 56_000,
+        }
+    }
+}
+
 /// ADC resolution
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Resolution {
     /// 12-bit resolution
     Bit12,
@@ -60,8 +273,31 @@ impl Default for Resolution {
     }
 }
 
+impl Resolution {
+    /// Conversion time of a single color channel in microseconds
+    ///
+    /// 100ms at 16-bit resolution and 6.25ms at 12-bit resolution.
+    pub const fn conversion_time_us(self) -> u32 {
+        match self {
+            Resolution::Bit12 => 6_250,
+            Resolution::Bit16 => 100_000,
+        }
+    }
+
+    /// Maximum count a color channel can reach
+    ///
+    /// 4095 at 12-bit resolution and 65535 at 16-bit resolution.
+    pub const fn max_count(self) -> u16 {
+        match self {
+            Resolution::Bit12 => 4095,
+            Resolution::Bit16 => 65535,
+        }
+    }
+}
+
 /// RGB data sensing range
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Range {
     /// 375 lux
     Lux375,
@@ -75,8 +311,19 @@ impl Default for Range {
     }
 }
 
+impl Range {
+    /// Illuminance at the full scale of the range in lux
+    pub const fn full_scale_lux(self) -> u32 {
+        match self {
+            Range::Lux375 => 375,
+            Range::Lux10000 => 10_000,
+        }
+    }
+}
+
 /// Interrupt pin (INT) mode
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum InterruptPinMode {
     /// Interrupts will be generated on exceeded thresholds. (ADC starts when writing to the config 1 register)
     Interrupt,
@@ -92,6 +339,7 @@ impl Default for InterruptPinMode {
 
 /// IR filtering range
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum IRFilteringRange {
     /// Lower range
     ///
@@ -111,6 +359,7 @@ impl Default for IRFilteringRange {
 
 /// Interrupt threshold assignment
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum InterruptThresholdAssignment {
     /// No interrupt (default)
     None,
@@ -128,11 +377,24 @@ impl Default for InterruptThresholdAssignment {
     }
 }
 
+/// Width of the band of interrupt thresholds around the current value
+///
+/// Used by [`recenter_interrupt_thresholds()`](struct.Isl29125.html#method.recenter_interrupt_thresholds).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ThresholdBand {
+    /// Percentage of the current value on each side
+    Percent(u8),
+    /// Counts at the current resolution on each side
+    Absolute(u16),
+}
+
 /// Fault count
 ///
 /// Number of consecutive fault events necessary to trigger interrupt.
 /// This is referred to as "persistence" in the documentation.
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum FaultCount {
     /// One (default)
     One,
@@ -150,8 +412,34 @@ impl Default for FaultCount {
     }
 }
 
+/// Complete sensor configuration
+///
+/// Can be applied at once with [`configure()`](struct.Isl29125.html#method.configure).
+/// The default value corresponds to the device configuration after power-on.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SensorConfig {
+    /// Operating mode
+    pub operating_mode: OperatingMode,
+    /// ADC resolution
+    pub resolution: Resolution,
+    /// RGB data sensing range
+    pub range: Range,
+    /// IR filtering
+    pub ir_filtering: IRFilteringRange,
+    /// Interrupt pin (INT) mode
+    pub interrupt_pin_mode: InterruptPinMode,
+    /// Color channel used for threshold value interrupt generation
+    pub interrupt_threshold_assignment: InterruptThresholdAssignment,
+    /// Number of consecutive fault events necessary to trigger an interrupt
+    pub fault_count: FaultCount,
+    /// Whether to generate an interrupt after a conversion is done
+    pub interrupt_on_conversion_done: bool,
+}
+
 /// Status
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Status {
     /// Whether an interrupt was triggered
     pub interrupt_triggered: bool,
@@ -165,6 +453,7 @@ pub struct Status {
 
 /// RGB conversion status
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ConversionStatus {
     /// No operation
     NoOperation,
@@ -224,6 +513,107 @@ mod tests {
         assert_eq!(FaultCount::One, FaultCount::default());
     }
 
+    #[test]
+    fn can_get_timing_at_compile_time() {
+        const CYCLE: u32 = OperatingMode::RedGreenBlue.cycle_time_us(Resolution::Bit16);
+        const CONVERSION: u32 = OperatingMode::RedGreen.conversion_time_us(Resolution::Bit12);
+        const MAX: u16 = Resolution::Bit12.max_count();
+        assert_eq!(300_000, CYCLE);
+        assert_eq!(6_250, CONVERSION);
+        assert_eq!(4095, MAX);
+    }
+
+    #[test]
+    fn can_get_channel_count() {
+        assert_eq!(0, OperatingMode::PowerDown.channel_count());
+        assert_eq!(0, OperatingMode::StandBy.channel_count());
+        assert_eq!(1, OperatingMode::BlueOnly.channel_count());
+        assert_eq!(2, OperatingMode::GreenBlue.channel_count());
+        assert_eq!(3, OperatingMode::RedGreenBlue.channel_count());
+    }
+
+    #[test]
+    fn no_conversion_time_without_channels() {
+        assert_eq!(
+            0,
+            OperatingMode::StandBy.conversion_time_us(Resolution::Bit16)
+        );
+        assert_eq!(0, OperatingMode::PowerDown.cycle_time_us(Resolution::Bit12));
+    }
+
+    #[test]
+    fn can_get_supply_current() {
+        assert_eq!(500, OperatingMode::PowerDown.supply_current_na());
+        assert_eq!(56_000, OperatingMode::RedOnly.supply_current_na());
+    }
+
+    #[test]
+    fn clamps_out_of_range_values_when_rescaling() {
+        let m = Measurement::new(65535, 4096, 4095, Resolution::Bit12);
+        let m16 = m.to_resolution(Resolution::Bit16);
+        assert_eq!((65535, 65535, 65535), (m16.red, m16.green, m16.blue));
+        assert_eq!(
+            65535,
+            rescale(u16::MAX, Resolution::Bit16, Resolution::Bit16)
+        );
+    }
+
+    #[test]
+    fn can_rescale_measurement() {
+        let m = Measurement::new(4095, 2048, 0, Resolution::Bit12);
+        let m16 = m.to_resolution(Resolution::Bit16);
+        assert_eq!((65535, 32776, 0), (m16.red, m16.green, m16.blue));
+        assert_eq!(Resolution::Bit16, m16.resolution);
+        let m12 = m16.to_resolution(Resolution::Bit12);
+        assert_eq!((4095, 2048, 0), (m12.red, m12.green, m12.blue));
+    }
+
+    #[test]
+    fn can_compare_measurements_at_different_resolutions() {
+        let m12 = Measurement::new(4095, 0, 100, Resolution::Bit12);
+        let m16 = Measurement::new(65535, 0, 1600, Resolution::Bit16);
+        assert_eq!(m12, m16);
+        assert_ne!(m12, Measurement::new(4095, 0, 100, Resolution::Bit16));
+    }
+
+    #[test]
+    fn can_normalize_measurement() {
+        let m = Measurement::new(4095, 0, 4095, Resolution::Bit12);
+        assert_eq!([1.0, 0.0, 1.0], m.normalized());
+        let m = Measurement::new(65535, 0, 0, Resolution::Bit16);
+        assert_eq!([1.0, 0.0, 0.0], m.normalized());
+    }
+
+    #[test]
+    fn can_get_channel_condition() {
+        use ChannelCondition::*;
+        assert_eq!(Saturated, ChannelCondition::of(65535, Resolution::Bit16));
+        assert_eq!(Normal, ChannelCondition::of(65534, Resolution::Bit16));
+        assert_eq!(Normal, ChannelCondition::of(256, Resolution::Bit16));
+        assert_eq!(UnderRange, ChannelCondition::of(255, Resolution::Bit16));
+        assert_eq!(Saturated, ChannelCondition::of(4095, Resolution::Bit12));
+        assert_eq!(Normal, ChannelCondition::of(4094, Resolution::Bit12));
+        assert_eq!(Normal, ChannelCondition::of(16, Resolution::Bit12));
+        assert_eq!(UnderRange, ChannelCondition::of(15, Resolution::Bit12));
+    }
+
+    #[test]
+    fn can_get_default_sensor_config() {
+        assert_eq!(
+            SensorConfig {
+                operating_mode: OperatingMode::PowerDown,
+                resolution: Resolution::Bit16,
+                range: Range::Lux375,
+                ir_filtering: IRFilteringRange::Lower(0),
+                interrupt_pin_mode: InterruptPinMode::Interrupt,
+                interrupt_threshold_assignment: InterruptThresholdAssignment::None,
+                fault_count: FaultCount::One,
+                interrupt_on_conversion_done: false,
+            },
+            SensorConfig::default()
+        );
+    }
+
     #[test]
     fn can_get_default_status() {
         assert_eq!(
@@ -236,4 +626,12 @@ mod tests {
             Status::default()
         );
     }
+
+    #[test]
+    fn can_compute_lux() {
+        let m = Measurement::new(0, 2048, 0, Resolution::Bit12);
+        assert!(libm::fabsf(m.lux(Range::Lux375) - 187.5) < 0.1);
+        let m = Measurement::new(0, 65535, 0, Resolution::Bit16);
+        assert_eq!(10_000.0, m.lux(Range::Lux10000));
+    }
 }
@@ -0,0 +1,352 @@
+//! White balance.
+//!
+//! A [`WhiteBalance`] stores per-channel gains computed from a white
+//! reference so that the reference is read as neutral gray. The gains can
+//! come from a measurement of a white target under the ambient light or be
+//! estimated continuously from the stream of measurements with an
+//! [`AutoWhiteBalance`].
+//!
+//! Both implement [`Filter`] so they can be chained with other filters:
+//!
+//! ```
+//! use isl29125::filter::{Filter, Median};
+//! use isl29125::white_balance::WhiteBalance;
+//! use isl29125::{Measurement, Resolution};
+//!
+//! let white = Measurement::new(20000, 40000, 10000, Resolution::Bit16);
+//! let wb = WhiteBalance::from_reference(&white).unwrap();
+//! let mut filter = Median::<3>::new().then(wb);
+//! let gray = Measurement::new(10000, 20000, 5000, Resolution::Bit16);
+//! assert_eq!(Measurement::new(20000, 20000, 20000, Resolution::Bit16), filter.update(gray));
+//! ```
+//!
+//! All computations use integer arithmetic.
+//!
+//! [`WhiteBalance`]: struct.WhiteBalance.html
+//! [`AutoWhiteBalance`]: struct.AutoWhiteBalance.html
+//! [`Filter`]: ../filter/trait.Filter.html
+
+use crate::{
+    filter::Filter, Error, InterruptPinMode, Isl29125, Measurement, OperatingMode, Resolution,
+};
+use embedded_hal::blocking::{delay::DelayUs, i2c};
+
+/// Number of fractional bits of the gains
+pub const GAIN_FRACTIONAL_BITS: u32 = 12;
+
+const UNITY_GAIN: u32 = 1 << GAIN_FRACTIONAL_BITS;
+
+/// Per-channel white balance gains
+///
+/// The gains are chosen so that the largest channel of the white reference
+/// is kept and the other ones are amplified to match it. Balanced values are
+/// clamped to the maximum count of the measurement resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WhiteBalance {
+    gains: [u32; 3],
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        Self::UNITY
+    }
+}
+
+impl WhiteBalance {
+    /// White balance leaving measurements unchanged
+    pub const UNITY: WhiteBalance = WhiteBalance {
+        gains: [UNITY_GAIN; 3],
+    };
+
+    /// Create a white balance from raw gains with
+    /// [`GAIN_FRACTIONAL_BITS`](constant.GAIN_FRACTIONAL_BITS.html)
+    /// fractional bits.
+    pub const fn from_gains(red: u32, green: u32, blue: u32) -> Self {
+        WhiteBalance {
+            gains: [red, green, blue],
+        }
+    }
+
+    /// Compute the gains from a measurement of a white reference.
+    ///
+    /// Returns `None` if any channel of the reference is zero.
+    pub fn from_reference(reference: &Measurement) -> Option<Self> {
+        let values = channels(&reference.to_resolution(Resolution::Bit16));
+        gains_from(&values.map(u64::from))
+    }
+
+    /// Compute the gains from the average of several measurements of a
+    /// white reference.
+    ///
+    /// Measurements at different resolutions can be mixed. Returns `None` if
+    /// `references` is empty or any channel of the average is zero.
+    pub fn from_references(references: &[Measurement]) -> Option<Self> {
+        let mut sums = [0u64; 3];
+        for reference in references {
+            let values = channels(&reference.to_resolution(Resolution::Bit16));
+            for (sum, value) in sums.iter_mut().zip(values.iter()) {
+                *sum += u64::from(*value);
+            }
+        }
+        // Dividing by the count is not needed since only ratios matter.
+        gains_from(&sums)
+    }
+
+    /// Measure a white reference `count` times and compute the gains from
+    /// the average.
+    ///
+    /// The sensor must be configured in `OperatingMode::RedGreenBlue` and be
+    /// looking at a white target under the light to be compensated. The ADC
+    /// conversion is restarted and each measurement is read after waiting
+    /// for a complete conversion cycle.
+    ///
+    /// If the sensor is not in `OperatingMode::RedGreenBlue`, the interrupt
+    /// pin is in `InterruptPinMode::SyncStart`, `count` is zero or any
+    /// channel of the average is zero, `Error::InvalidInputData` is returned.
+    pub fn capture<I2C, E, D>(
+        sensor: &mut Isl29125<I2C>,
+        delay: &mut D,
+        count: usize,
+    ) -> Result<Self, Error<E>>
+    where
+        I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+        D: DelayUs<u32>,
+    {
+        if sensor.operating_mode() != OperatingMode::RedGreenBlue
+            || sensor.interrupt_pin_mode() == InterruptPinMode::SyncStart
+            || count == 0
+        {
+            return Err(Error::InvalidInputData);
+        }
+        let cycle_time = sensor.operating_mode().cycle_time_us(sensor.resolution());
+        sensor.restart_conversion()?;
+        let mut sums = [0u64; 3];
+        for _ in 0..count {
+            delay.delay_us(cycle_time);
+            let m = sensor.read()?.to_resolution(Resolution::Bit16);
+            for (sum, value) in sums.iter_mut().zip(channels(&m).iter()) {
+                *sum += u64::from(*value);
+            }
+        }
+        gains_from(&sums).ok_or(Error::InvalidInputData)
+    }
+
+    /// Gains with [`GAIN_FRACTIONAL_BITS`](constant.GAIN_FRACTIONAL_BITS.html)
+    /// fractional bits (red, green, blue)
+    pub const fn gains(&self) -> [u32; 3] {
+        self.gains
+    }
+
+    /// Return the white-balanced measurement.
+    pub fn apply(&self, measurement: &Measurement) -> Measurement {
+        let max = u64::from(measurement.resolution.max_count());
+        let values = channels(measurement);
+        let balance = |i: usize| {
+            let value = u64::from(values[i]) * u64::from(self.gains[i]);
+            ((value + u64::from(UNITY_GAIN / 2)) >> GAIN_FRACTIONAL_BITS).min(max) as u16
+        };
+        Measurement::new(balance(0), balance(1), balance(2), measurement.resolution)
+    }
+}
+
+impl Filter for WhiteBalance {
+    fn update(&mut self, measurement: Measurement) -> Measurement {
+        self.apply(&measurement)
+    }
+
+    fn reset(&mut self) {}
+}
+
+/// Illuminant estimation method used by [`AutoWhiteBalance`](struct.AutoWhiteBalance.html)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AutoWhiteBalanceMethod {
+    /// Assume the average of the scene is gray (default)
+    GrayWorld,
+    /// Assume the brightest values of each channel correspond to white
+    WhitePatch,
+}
+
+impl Default for AutoWhiteBalanceMethod {
+    fn default() -> Self {
+        AutoWhiteBalanceMethod::GrayWorld
+    }
+}
+
+/// Automatic white balance estimating the illuminant from the measurements
+///
+/// For `GrayWorld` the illuminant estimate is the exponentially smoothed
+/// average of the measurements. For `WhitePatch` each channel of the estimate
+/// follows the maximum immediately and decays towards lower values with the
+/// same smoothing. `alpha` is given in 1/256 units like in
+/// [`ExponentialSmoothing`](../filter/struct.ExponentialSmoothing.html),
+/// with lower values adapting more slowly.
+///
+/// Until the estimate has a non-zero value in every channel, measurements
+/// are returned unchanged.
+#[derive(Debug, Clone)]
+pub struct AutoWhiteBalance {
+    method: AutoWhiteBalanceMethod,
+    alpha: u16,
+    // Illuminant estimate at 16-bit scale with 8 fractional bits.
+    estimate: Option<[u32; 3]>,
+}
+
+impl AutoWhiteBalance {
+    /// Create a new automatic white balance.
+    ///
+    /// `alpha` is given in 1/256 units and will be clamped to `[1-256]`.
+    pub fn new(method: AutoWhiteBalanceMethod, alpha: u16) -> Self {
+        AutoWhiteBalance {
+            method,
+            alpha: alpha.clamp(1, 256),
+            estimate: None,
+        }
+    }
+
+    /// Estimation method
+    pub fn method(&self) -> AutoWhiteBalanceMethod {
+        self.method
+    }
+
+    /// Current white balance
+    ///
+    /// Returns `WhiteBalance::UNITY` until the estimate has a non-zero value
+    /// in every channel.
+    pub fn white_balance(&self) -> WhiteBalance {
+        self.estimate
+            .and_then(|estimate| gains_from(&estimate.map(u64::from)))
+            .unwrap_or(WhiteBalance::UNITY)
+    }
+}
+
+impl Filter for AutoWhiteBalance {
+    fn update(&mut self, measurement: Measurement) -> Measurement {
+        let values = channels(&measurement.to_resolution(Resolution::Bit16));
+        let estimate = match self.estimate {
+            None => values.map(|v| u32::from(v) << 8),
+            Some(estimate) => {
+                let mut next = [0; 3];
+                for (i, next) in next.iter_mut().enumerate() {
+                    let input = i64::from(values[i]) << 8;
+                    let previous = i64::from(estimate[i]);
+                    let smoothed = previous + (i64::from(self.alpha) * (input - previous)) / 256;
+                    *next = match self.method {
+                        AutoWhiteBalanceMethod::GrayWorld => smoothed,
+                        AutoWhiteBalanceMethod::WhitePatch => smoothed.max(input),
+                    } as u32;
+                }
+                next
+            }
+        };
+        self.estimate = Some(estimate);
+        self.white_balance().apply(&measurement)
+    }
+
+    fn reset(&mut self) {
+        self.estimate = None;
+    }
+}
+
+fn channels(m: &Measurement) -> [u16; 3] {
+    [m.red, m.green, m.blue]
+}
+
+/// Gains bringing every channel to the largest one.
+fn gains_from(reference: &[u64; 3]) -> Option<WhiteBalance> {
+    if reference.contains(&0) {
+        return None;
+    }
+    let target = reference.iter().copied().max().unwrap_or(0);
+    let gain = |i: usize| {
+        let value = reference[i];
+        (((target << GAIN_FRACTIONAL_BITS) + value / 2) / value) as u32
+    };
+    Some(WhiteBalance::from_gains(gain(0), gain(1), gain(2)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn m(red: u16, green: u16, blue: u16) -> Measurement {
+        Measurement::new(red, green, blue, Resolution::Bit16)
+    }
+
+    #[test]
+    fn unity_leaves_measurements_unchanged() {
+        let measurement = m(1, 30000, 65535);
+        assert_eq!(measurement, WhiteBalance::default().apply(&measurement));
+    }
+
+    #[test]
+    fn reference_is_balanced_to_gray() {
+        let white = m(20000, 40000, 10000);
+        let wb = WhiteBalance::from_reference(&white).unwrap();
+        assert_eq!([2 * UNITY_GAIN, UNITY_GAIN, 4 * UNITY_GAIN], wb.gains());
+        assert_eq!(m(40000, 40000, 40000), wb.apply(&white));
+        assert_eq!(m(65535, 40000, 65535), wb.apply(&m(40000, 40000, 40000)));
+    }
+
+    #[test]
+    fn cannot_use_reference_with_zero_channel() {
+        assert_eq!(None, WhiteBalance::from_reference(&m(100, 0, 100)));
+        assert_eq!(None, WhiteBalance::from_references(&[]));
+    }
+
+    #[test]
+    fn averages_several_references() {
+        let twelve_bit = Measurement::new(1000, 2000, 1500, Resolution::Bit12);
+        let wb = WhiteBalance::from_references(&[m(18000, 36000, 30000), twelve_bit]).unwrap();
+        let expected = WhiteBalance::from_reference(&m(17000, 34000, 27000)).unwrap();
+        let diff = |i: usize| wb.gains()[i].abs_diff(expected.gains()[i]);
+        assert!(diff(0) <= 2 && diff(1) <= 2 && diff(2) <= 2, "{:?}", wb);
+    }
+
+    #[test]
+    fn applies_at_measurement_resolution() {
+        let wb = WhiteBalance::from_reference(&m(10000, 20000, 20000)).unwrap();
+        let balanced = wb.apply(&Measurement::new(1000, 1000, 3000, Resolution::Bit12));
+        assert_eq!(
+            Measurement::new(2000, 1000, 3000, Resolution::Bit12),
+            balanced
+        );
+        assert_eq!(Resolution::Bit12, balanced.resolution);
+        assert_eq!(
+            4095,
+            wb.apply(&Measurement::new(3000, 0, 0, Resolution::Bit12))
+                .red
+        );
+    }
+
+    #[test]
+    fn gray_world_converges_to_scene_average() {
+        let mut awb = AutoWhiteBalance::new(AutoWhiteBalanceMethod::GrayWorld, 64);
+        for _ in 0..100 {
+            awb.update(m(10000, 20000, 5000));
+            awb.update(m(30000, 40000, 15000));
+        }
+        let out = awb.update(m(20000, 30000, 10000));
+        assert!(out.red.abs_diff(out.green) < 1000, "{:?}", out);
+        assert!(out.blue.abs_diff(out.green) < 1000, "{:?}", out);
+    }
+
+    #[test]
+    fn white_patch_follows_brightest_values() {
+        let mut awb = AutoWhiteBalance::new(AutoWhiteBalanceMethod::WhitePatch, 1);
+        awb.update(m(100, 100, 100));
+        awb.update(m(20000, 40000, 10000));
+        let out = awb.update(m(100, 200, 50));
+        assert_eq!(m(200, 200, 200), out);
+        awb.reset();
+        assert_eq!(WhiteBalance::UNITY, awb.white_balance());
+    }
+
+    #[test]
+    fn auto_white_balance_waits_for_all_channels() {
+        let mut awb = AutoWhiteBalance::new(AutoWhiteBalanceMethod::GrayWorld, 256);
+        assert_eq!(m(0, 100, 200), awb.update(m(0, 100, 200)));
+        assert_eq!(WhiteBalance::UNITY, awb.white_balance());
+    }
+}
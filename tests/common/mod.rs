@@ -1,4 +1,4 @@
-use embedded_hal_mock::i2c::{Mock as I2cMock, Transaction as I2cTrans};
+use embedded_hal_mock::eh0::i2c::{Mock as I2cMock, Transaction as I2cTrans};
 use isl29125::Isl29125;
 
 pub struct Register;
@@ -15,7 +15,9 @@ impl Register {
     pub const BLUE_L: u8 = 0x0D;
 }
 
+#[allow(unused)]
 pub struct BitFlags;
+#[allow(unused)]
 impl BitFlags {
     pub const SYNC: u8 = 1 << 5;
     pub const RESOLUTION: u8 = 1 << 4;
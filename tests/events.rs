@@ -0,0 +1,60 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::i2c::Transaction as I2cTrans;
+use isl29125::{
+    events::{EventDetector, Quantity, Window, Zone},
+    InterruptThresholdAssignment, Range,
+};
+
+fn read_green(green: u16) -> I2cTrans {
+    let [low, high] = green.to_le_bytes();
+    I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![low, high, 0, 0, 0, 0])
+}
+
+fn lux_detector() -> EventDetector<1> {
+    EventDetector::new([Window::new(Quantity::Lux, 10.0, 5000.0)])
+}
+
+#[test]
+fn can_poll_at_current_range() {
+    let mut sensor = new(&[
+        read_green(0x8000),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE]),
+        read_green(0x8000),
+    ]);
+    let mut detector = lux_detector();
+    // About 187 lux at 375 lux range
+    assert_eq!([None], detector.poll(&mut sensor).unwrap());
+    sensor.set_range(Range::Lux10000).unwrap();
+    // About 5000 lux at 10000 lux range
+    let events = detector.poll(&mut sensor).unwrap();
+    assert_eq!(Some(Zone::Above), events[0].map(|e| e.to));
+    destroy(sensor);
+}
+
+#[test]
+fn polls_without_status_if_no_interrupt_assigned() {
+    let mut sensor = new(&[read_green(0)]);
+    let mut detector = lux_detector();
+    let events = detector.poll_if_triggered(&mut sensor).unwrap();
+    assert_eq!(Some(Zone::Below), events[0].map(|e| e.to));
+    destroy(sensor);
+}
+
+#[test]
+fn uses_hardware_interrupt_as_hint() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, 1]),
+        I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![BF::RGBTHF]),
+        read_green(0),
+    ]);
+    sensor
+        .set_interrupt_threshold_assignment(InterruptThresholdAssignment::Green)
+        .unwrap();
+    let mut detector = lux_detector();
+    assert_eq!([None], detector.poll_if_triggered(&mut sensor).unwrap());
+    let events = detector.poll_if_triggered(&mut sensor).unwrap();
+    assert_eq!(Some(Zone::Below), events[0].map(|e| e.to));
+    destroy(sensor);
+}
@@ -0,0 +1,189 @@
+#![cfg(feature = "std")]
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{
+    i2c::{Mock as I2cMock, Transaction as I2cTrans},
+    MockError,
+};
+use isl29125::{
+    iio::{IioDevice, IioError},
+    Error,
+};
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+fn directory(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("isl29125-iio-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn read(red: u8, green: u8, blue: u8) -> I2cTrans {
+    I2cTrans::write_read(
+        ADDR,
+        vec![Register::GREEN_L],
+        vec![green, 0, red, 0, blue, 0],
+    )
+}
+
+fn attribute(iio: &IioDevice<I2cMock>, name: &str) -> String {
+    fs::read_to_string(iio.directory().join(name))
+        .unwrap()
+        .trim_end()
+        .to_string()
+}
+
+fn write(iio: &IioDevice<I2cMock>, name: &str, value: &str) {
+    fs::write(iio.directory().join(name), value).unwrap();
+}
+
+#[test]
+fn creates_attributes() {
+    let iio = IioDevice::new(new(&[]), directory("create")).unwrap();
+    assert_eq!("isl29125", attribute(&iio, "name"));
+    assert_eq!("0", attribute(&iio, "in_intensity_red_raw"));
+    assert_eq!("0.005722", attribute(&iio, "in_intensity_scale"));
+    assert_eq!(
+        "0.005722 0.152590 0.091575 2.442002",
+        attribute(&iio, "in_intensity_scale_available")
+    );
+    assert_eq!("0.100000", attribute(&iio, "in_intensity_integration_time"));
+    assert_eq!(
+        "0.100000 0.006250",
+        attribute(&iio, "in_intensity_integration_time_available")
+    );
+    assert_eq!("power_down", attribute(&iio, "operating_mode"));
+    assert_eq!("0", attribute(&iio, "in_intensity_ir_compensation"));
+    assert_eq!("0", attribute(&iio, "in_intensity_thresh_falling_value"));
+    assert_eq!("65535", attribute(&iio, "in_intensity_thresh_rising_value"));
+    assert_eq!("none", attribute(&iio, "in_intensity_thresh_channel"));
+    assert_eq!("1", attribute(&iio, "in_intensity_thresh_period"));
+    assert_eq!(
+        "1 2 4 8",
+        attribute(&iio, "in_intensity_thresh_period_available")
+    );
+    assert_eq!("interrupt", attribute(&iio, "interrupt_pin_mode"));
+    assert_eq!(
+        "interrupt sync_start",
+        attribute(&iio, "interrupt_pin_mode_available")
+    );
+    assert_eq!("0", attribute(&iio, "conversion_done_interrupt_en"));
+    destroy(iio.destroy());
+}
+
+#[test]
+fn refreshes_measurements() {
+    let mut iio = IioDevice::new(new(&[read(1, 0x80, 3)]), directory("read")).unwrap();
+    iio.update().unwrap();
+    assert_eq!("1", attribute(&iio, "in_intensity_red_raw"));
+    assert_eq!("128", attribute(&iio, "in_intensity_green_raw"));
+    assert_eq!("3", attribute(&iio, "in_intensity_blue_raw"));
+    assert_eq!("0.732", attribute(&iio, "in_illuminance_input"));
+    destroy(iio.destroy());
+}
+
+#[test]
+fn writing_scale_sets_range_and_resolution() {
+    let sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE | BF::RESOLUTION]),
+        read(0, 0, 0),
+    ]);
+    let mut iio = IioDevice::new(sensor, directory("scale")).unwrap();
+    write(&iio, "in_intensity_scale", "2.442\n");
+    iio.update().unwrap();
+    assert_eq!("2.442002", attribute(&iio, "in_intensity_scale"));
+    assert_eq!("0.006250", attribute(&iio, "in_intensity_integration_time"));
+    assert_eq!("4095", attribute(&iio, "in_intensity_thresh_rising_value"));
+    destroy(iio.destroy());
+}
+
+#[test]
+fn restores_range_when_setting_resolution_fails() {
+    let sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE | BF::RESOLUTION])
+            .with_error(MockError::Io(ErrorKind::Other)),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 0]),
+        read(0, 0, 0),
+    ]);
+    let mut iio = IioDevice::new(sensor, directory("scale-error")).unwrap();
+    write(&iio, "in_intensity_scale", "2.442");
+    match iio.update() {
+        Err(IioError::Device(Error::I2C(_))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    assert_eq!("0.005722", attribute(&iio, "in_intensity_scale"));
+    destroy(iio.destroy());
+}
+
+#[test]
+fn applies_interrupt_attributes() {
+    let sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::SYNC]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, BF::CONVEN]),
+        read(0, 0, 0),
+    ]);
+    let mut iio = IioDevice::new(sensor, directory("interrupt")).unwrap();
+    write(&iio, "conversion_done_interrupt_en", "1");
+    write(&iio, "interrupt_pin_mode", "sync_start");
+    iio.update().unwrap();
+    assert_eq!("sync_start", attribute(&iio, "interrupt_pin_mode"));
+    assert_eq!("1", attribute(&iio, "conversion_done_interrupt_en"));
+    destroy(iio.destroy());
+}
+
+#[test]
+fn applies_configuration_attributes() {
+    let sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, BF::IR_OFFSET | 4]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION | 5]),
+        I2cTrans::write(ADDR, vec![Register::THL, 10, 0, 0xFF, 0x0F]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, 2]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, 2 | (2 << 2)]),
+        read(0, 0, 0),
+    ]);
+    let mut iio = IioDevice::new(sensor, directory("config")).unwrap();
+    write(&iio, "in_intensity_integration_time", "0.00625");
+    write(&iio, "in_intensity_ir_compensation", "110");
+    write(&iio, "operating_mode", "red_green_blue");
+    write(&iio, "in_intensity_thresh_falling_value", "10");
+    write(&iio, "in_intensity_thresh_channel", "red");
+    write(&iio, "in_intensity_thresh_period", "4");
+    iio.update().unwrap();
+    assert_eq!("110", attribute(&iio, "in_intensity_ir_compensation"));
+    assert_eq!("red_green_blue", attribute(&iio, "operating_mode"));
+    assert_eq!("10", attribute(&iio, "in_intensity_thresh_falling_value"));
+    assert_eq!("red", attribute(&iio, "in_intensity_thresh_channel"));
+    assert_eq!("4", attribute(&iio, "in_intensity_thresh_period"));
+    destroy(iio.destroy());
+}
+
+#[test]
+fn reverts_invalid_values() {
+    let mut iio = IioDevice::new(new(&[read(0, 0, 0)]), directory("invalid")).unwrap();
+    write(&iio, "in_intensity_scale", "1.0");
+    write(&iio, "operating_mode", "ultraviolet");
+    match iio.update() {
+        Err(IioError::Device(Error::InvalidInputData)) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    assert_eq!("0.005722", attribute(&iio, "in_intensity_scale"));
+    assert_eq!("power_down", attribute(&iio, "operating_mode"));
+    destroy(iio.destroy());
+}
+
+#[test]
+fn returns_device_errors_and_keeps_attributes() {
+    let sensor = new(&[
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    let mut iio = IioDevice::new(sensor, directory("error")).unwrap();
+    match iio.update() {
+        Err(IioError::Device(Error::I2C(_))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    assert_eq!("0", attribute(&iio, "in_intensity_red_raw"));
+    destroy(iio.destroy());
+}
@@ -0,0 +1,108 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{delay::NoopDelay, i2c::Transaction as I2cTrans, MockError};
+use isl29125::{Error, IRFilteringRange, InterruptPinMode, OperatingMode};
+use std::io::ErrorKind;
+
+const RGB_MODE: u8 = 5;
+
+fn read(red: u8, green: u8, blue: u8) -> I2cTrans {
+    I2cTrans::write_read(
+        ADDR,
+        vec![Register::GREEN_L],
+        vec![0, green, 0, red, 0, blue],
+    )
+}
+
+#[test]
+fn can_measure_ir_ratio() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 20]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        read(0x40, 0x30, 0x10),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, BF::IR_OFFSET | 63]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        read(0x20, 0x30, 0x10),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 20]),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor
+        .set_ir_filtering(IRFilteringRange::Lower(20))
+        .unwrap();
+    let ratio = sensor.measure_ir_ratio(&mut NoopDelay).unwrap();
+    assert_eq!(0.25, ratio);
+    destroy(sensor);
+}
+
+#[test]
+fn restores_ir_filtering_after_read_error() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 1]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 1]),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0]),
+    ]);
+    sensor.set_operating_mode(OperatingMode::GreenOnly).unwrap();
+    match sensor.measure_ir_ratio(&mut NoopDelay) {
+        Err(Error::I2C(_)) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_ir_ratio_in_the_dark() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        read(0, 0, 0),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, BF::IR_OFFSET | 63]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        read(0, 0, 0),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0]),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.measure_ir_ratio(&mut NoopDelay) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_ir_ratio_without_channels() {
+    let mut sensor = new(&[]);
+    match sensor.measure_ir_ratio(&mut NoopDelay) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_ir_ratio_in_sync_start_mode() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE | BF::SYNC]),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor
+        .set_interrupt_pin_mode(InterruptPinMode::SyncStart)
+        .unwrap();
+    match sensor.measure_ir_ratio(&mut NoopDelay) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
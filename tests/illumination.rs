@@ -0,0 +1,188 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{
+    delay::NoopDelay,
+    digital::{Mock as PinMock, State, Transaction as PinTrans},
+    i2c::Transaction as I2cTrans,
+    MockError,
+};
+use isl29125::{
+    illumination::IlluminationError, ChannelCondition, Error, InterruptPinMode, Measurement,
+    OperatingMode, Resolution,
+};
+use std::io::ErrorKind;
+
+fn restart() -> I2cTrans {
+    I2cTrans::write(ADDR, vec![Register::CONFIG1, 5])
+}
+
+fn read(red: u8, green: u8, blue: u8) -> I2cTrans {
+    I2cTrans::write_read(
+        ADDR,
+        vec![Register::GREEN_L],
+        vec![green, 0, red, 0, blue, 0],
+    )
+}
+
+#[test]
+fn can_measure_illuminated() {
+    let mut sensor = new(&[
+        restart(),
+        restart(),
+        read(10, 20, 30),
+        restart(),
+        read(50, 15, 40),
+    ]);
+    let mut led = PinMock::new(&[
+        PinTrans::set(State::Low),
+        PinTrans::set(State::High),
+        PinTrans::set(State::Low),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let m = sensor
+        .measure_illuminated(&mut led, &mut NoopDelay)
+        .unwrap();
+    assert_eq!(
+        Measurement::new(40, 0, 10, Resolution::Bit16),
+        m.reflectance
+    );
+    assert_eq!(Measurement::new(10, 20, 30, Resolution::Bit16), m.ambient);
+    assert_eq!(ChannelCondition::UnderRange, m.red);
+    assert!(!m.is_saturated());
+    led.done();
+    destroy(sensor);
+}
+
+#[test]
+fn reports_saturation() {
+    let mut sensor = new(&[
+        restart(),
+        restart(),
+        read(0, 0, 0),
+        restart(),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0, 0x10, 0xFF, 0xFF, 0, 0x10],
+        ),
+    ]);
+    let mut led = PinMock::new(&[
+        PinTrans::set(State::Low),
+        PinTrans::set(State::High),
+        PinTrans::set(State::Low),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let m = sensor
+        .measure_illuminated(&mut led, &mut NoopDelay)
+        .unwrap();
+    assert_eq!(ChannelCondition::Saturated, m.red);
+    assert_eq!(ChannelCondition::Normal, m.green);
+    assert!(m.is_saturated());
+    led.done();
+    destroy(sensor);
+}
+
+#[test]
+fn switches_illuminator_off_after_read_error() {
+    let mut sensor = new(&[
+        restart(),
+        restart(),
+        read(0, 0, 0),
+        restart(),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    let mut led = PinMock::new(&[
+        PinTrans::set(State::Low),
+        PinTrans::set(State::High),
+        PinTrans::set(State::Low),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.measure_illuminated(&mut led, &mut NoopDelay) {
+        Err(IlluminationError::Device(Error::I2C(_))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    led.done();
+    destroy(sensor);
+}
+
+#[test]
+fn returns_pin_error() {
+    let mut sensor = new(&[restart()]);
+    let mut led =
+        PinMock::new(&[PinTrans::set(State::Low).with_error(MockError::Io(ErrorKind::Other))]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.measure_illuminated(&mut led, &mut NoopDelay) {
+        Err(IlluminationError::Pin(_)) => (),
+        _ => panic!("Should return pin error."),
+    }
+    led.done();
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_without_channels() {
+    let mut sensor = new(&[]);
+    let mut led = PinMock::new(&[]);
+    match sensor.measure_illuminated(&mut led, &mut NoopDelay) {
+        Err(IlluminationError::Device(Error::InvalidInputData)) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    led.done();
+    destroy(sensor);
+}
+
+#[test]
+fn returns_read_error_before_pin_error() {
+    let mut sensor = new(&[
+        restart(),
+        restart(),
+        read(0, 0, 0),
+        restart(),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    let mut led = PinMock::new(&[
+        PinTrans::set(State::Low),
+        PinTrans::set(State::High),
+        PinTrans::set(State::Low).with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.measure_illuminated(&mut led, &mut NoopDelay) {
+        Err(IlluminationError::Device(Error::I2C(_))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    led.done();
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_in_sync_start_mode() {
+    let mut sensor = new(&[
+        restart(),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5 | BF::SYNC]),
+    ]);
+    let mut led = PinMock::new(&[]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor
+        .set_interrupt_pin_mode(InterruptPinMode::SyncStart)
+        .unwrap();
+    match sensor.measure_illuminated(&mut led, &mut NoopDelay) {
+        Err(IlluminationError::Device(Error::InvalidInputData)) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    led.done();
+    destroy(sensor);
+}
@@ -1,9 +1,10 @@
 mod common;
 use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
-use embedded_hal_mock::i2c::Transaction as I2cTrans;
+use embedded_hal_mock::eh0::i2c::Transaction as I2cTrans;
 use isl29125::{
-    ConversionStatus as CS, FaultCount, IRFilteringRange, InterruptPinMode,
-    InterruptThresholdAssignment, OperatingMode, Range, Resolution, Status,
+    sampler::Sampler, ConversionStatus as CS, Error, FaultCount, IRFilteringRange,
+    InterruptPinMode, InterruptThresholdAssignment, OperatingMode, Range, Resolution, SensorConfig,
+    Status, ThresholdBand,
 };
 
 #[test]
@@ -203,6 +204,59 @@ fn can_set_interrupt_thresholds() {
     destroy(sensor);
 }
 
+#[test]
+fn can_recenter_interrupt_thresholds_by_percentage() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, 1]),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0xE8, 0x03]),
+        I2cTrans::write(ADDR, vec![Register::THL, 0x84, 0x03, 0x4C, 0x04]),
+    ]);
+    sensor
+        .set_interrupt_threshold_assignment(InterruptThresholdAssignment::Green)
+        .unwrap();
+    let value = sensor
+        .recenter_interrupt_thresholds(ThresholdBand::Percent(10))
+        .unwrap();
+    assert_eq!(1000, value);
+    destroy(sensor);
+}
+
+#[test]
+fn recentered_interrupt_thresholds_are_limited_to_resolution() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, 3]),
+        I2cTrans::write_read(ADDR, vec![Register::BLUE_L], vec![0x00, 0x0F]),
+        I2cTrans::write(ADDR, vec![Register::THL, 0x00, 0x0D, 0xFF, 0x0F]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, 2]),
+        I2cTrans::write_read(ADDR, vec![Register::RED_L], vec![0x00, 0x00]),
+        I2cTrans::write(ADDR, vec![Register::THL, 0x00, 0x00, 0x01, 0x00]),
+    ]);
+    sensor.set_resolution(Resolution::Bit12).unwrap();
+    sensor
+        .set_interrupt_threshold_assignment(InterruptThresholdAssignment::Blue)
+        .unwrap();
+    sensor
+        .recenter_interrupt_thresholds(ThresholdBand::Absolute(0x200))
+        .unwrap();
+    sensor
+        .set_interrupt_threshold_assignment(InterruptThresholdAssignment::Red)
+        .unwrap();
+    sensor
+        .recenter_interrupt_thresholds(ThresholdBand::Percent(50))
+        .unwrap();
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_recenter_interrupt_thresholds_without_assignment() {
+    let mut sensor = new(&[]);
+    sensor
+        .recenter_interrupt_thresholds(ThresholdBand::Percent(10))
+        .expect_err("Should return error.");
+    destroy(sensor);
+}
+
 macro_rules! get_status_test {
     ($name:ident, $value:expr, $expected_member:ident, $expected_value:expr) => {
         #[test]
@@ -249,3 +303,109 @@ macro_rules! get_color_test {
 get_color_test!(get_color_red, RED_L, red);
 get_color_test!(get_color_green, GREEN_L, green);
 get_color_test!(get_color_blue, BLUE_L, blue);
+
+#[test]
+fn can_sample_into_sampler() {
+    let mut sensor = new(&[I2cTrans::write_read(
+        ADDR,
+        vec![Register::GREEN_L],
+        vec![0x78, 0x56, 0x34, 0x12, 0xBC, 0x9A],
+    )]);
+    let mut sampler = Sampler::<4>::new();
+    let m = sampler.sample(&mut sensor, 1234).unwrap();
+    assert_eq!(1, sampler.len());
+    assert_eq!(1234, sampler.latest().unwrap().timestamp);
+    assert_eq!(m, sampler.latest().unwrap().measurement);
+    destroy(sensor);
+}
+
+#[test]
+fn can_configure() {
+    let mut sensor = new(&[
+        I2cTrans::write(
+            ADDR,
+            vec![Register::CONFIG1, BF::SYNC | BF::RANGE | BF::RESOLUTION | 5],
+        ),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, BF::IR_OFFSET | 12]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG3, BF::CONVEN | 2 << 2 | 3]),
+    ]);
+    sensor
+        .configure(&SensorConfig {
+            operating_mode: OperatingMode::RedGreenBlue,
+            resolution: Resolution::Bit12,
+            range: Range::Lux10000,
+            ir_filtering: IRFilteringRange::Higher(12),
+            interrupt_pin_mode: InterruptPinMode::SyncStart,
+            interrupt_threshold_assignment: InterruptThresholdAssignment::Blue,
+            fault_count: FaultCount::Four,
+            interrupt_on_conversion_done: true,
+        })
+        .unwrap();
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_configure_wrong_ir_adjust() {
+    let mut sensor = new(&[]);
+    let config = SensorConfig {
+        ir_filtering: IRFilteringRange::Lower(64),
+        ..SensorConfig::default()
+    };
+    sensor.configure(&config).expect_err("Should return error.");
+    destroy(sensor);
+}
+
+#[test]
+fn measurement_carries_resolution() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION]),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0xFF, 0x0F, 0x00, 0x08, 0x00, 0x00],
+        ),
+    ]);
+    sensor.set_resolution(Resolution::Bit12).unwrap();
+    let m = sensor.read().unwrap();
+    assert_eq!(Resolution::Bit12, m.resolution);
+    let m16 = m.to_resolution(Resolution::Bit16);
+    assert_eq!((32776, 65535, 0), (m16.red, m16.green, m16.blue));
+    destroy(sensor);
+}
+
+#[test]
+fn rescales_thresholds_on_resolution_change() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::THL, 0x00, 0x10, 0xFF, 0xFF]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION]),
+        I2cTrans::write(ADDR, vec![Register::THL, 0x00, 0x01, 0xFF, 0x0F]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION | 5]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        I2cTrans::write(ADDR, vec![Register::THL, 0x00, 0x10, 0xFF, 0xFF]),
+    ]);
+    sensor.set_interrupt_thresholds(0x1000, 0xFFFF).unwrap();
+    sensor.set_resolution(Resolution::Bit12).unwrap();
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor.set_resolution(Resolution::Bit16).unwrap();
+    destroy(sensor);
+}
+
+#[test]
+fn rejects_thresholds_above_max_count_and_clamps_on_rescale() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION]),
+        I2cTrans::write(ADDR, vec![Register::THL, 0x00, 0x00, 0xFF, 0x0F]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 0]),
+        I2cTrans::write(ADDR, vec![Register::THL, 0x00, 0x00, 0xFF, 0xFF]),
+    ]);
+    sensor.set_resolution(Resolution::Bit12).unwrap();
+    match sensor.set_interrupt_thresholds(0, 65535) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    sensor.set_interrupt_thresholds(0, 4095).unwrap();
+    sensor.set_resolution(Resolution::Bit16).unwrap();
+    destroy(sensor);
+}
@@ -0,0 +1,111 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{delay::NoopDelay, i2c::Transaction as I2cTrans, MockError};
+use isl29125::{Error, IRFilteringRange, InterruptPinMode, Measurement, OperatingMode, Resolution};
+use std::io::ErrorKind;
+
+const RGB_MODE: u8 = 5;
+
+fn sweep_transactions(best: u8) -> Vec<I2cTrans> {
+    let mut transactions = vec![I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE])];
+    for config2 in (0..64).chain(0x80..0x80 + 64) {
+        // The best setting returns the target proportions. Others are too red.
+        let red = if config2 == best { 0x10 } else { 0x30 };
+        transactions.push(I2cTrans::write(ADDR, vec![Register::CONFIG2, config2]));
+        transactions.push(I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]));
+        transactions.push(I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0x20, 0, red, 0, 0x10, 0],
+        ));
+    }
+    transactions.push(I2cTrans::write(ADDR, vec![Register::CONFIG2, best]));
+    transactions
+}
+
+#[test]
+fn can_tune_ir_filtering_to_target() {
+    let mut sensor = new(&sweep_transactions(0x80 | 12));
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let target = Measurement::new(1, 2, 1, Resolution::Bit16);
+    let setting = sensor.tune_ir_filtering(&mut NoopDelay, target).unwrap();
+    assert_eq!(IRFilteringRange::Higher(12), setting);
+    destroy(sensor);
+}
+
+#[test]
+fn can_tune_ir_filtering_with_objective() {
+    let mut sensor = new(&sweep_transactions(33));
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let setting = sensor
+        .tune_ir_filtering_with(&mut NoopDelay, |m| u64::from(m.red))
+        .unwrap();
+    assert_eq!(IRFilteringRange::Lower(33), setting);
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_tune_ir_filtering_to_target_without_rgb_mode() {
+    let mut sensor = new(&[]);
+    let target = Measurement::new(1, 1, 1, Resolution::Bit16);
+    sensor
+        .tune_ir_filtering(&mut NoopDelay, target)
+        .expect_err("Should return error.");
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_tune_ir_filtering_when_powered_down() {
+    let mut sensor = new(&[]);
+    sensor
+        .tune_ir_filtering_with(&mut NoopDelay, |_| 0)
+        .expect_err("Should return error.");
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_tune_ir_filtering_in_sync_start_mode() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE | BF::SYNC]),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor
+        .set_interrupt_pin_mode(InterruptPinMode::SyncStart)
+        .unwrap();
+    match sensor.tune_ir_filtering_with(&mut NoopDelay, |_| 0) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn restores_ir_filtering_after_error() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0x80 | 5]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+        I2cTrans::write(ADDR, vec![Register::CONFIG2, 0x80 | 5]),
+    ]);
+    sensor
+        .set_ir_filtering(IRFilteringRange::Higher(5))
+        .unwrap();
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.tune_ir_filtering_with(&mut NoopDelay, |_| 0) {
+        Err(Error::I2C(_)) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    destroy(sensor);
+}
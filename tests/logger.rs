@@ -0,0 +1,198 @@
+#![cfg(feature = "std")]
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{i2c::Transaction as I2cTrans, MockError};
+use isl29125::{
+    logger::{Format, Logger, LoggerConfig, LoggerError, Rotation},
+    Error, OperatingMode,
+};
+use std::{fs, io::ErrorKind, path::PathBuf, time::Duration};
+
+fn directory(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("isl29125-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn sample(status: u8, red: u8, green: u8, blue: u8) -> [I2cTrans; 2] {
+    [
+        I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![status]),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![green, 0, red, 0, blue, 0],
+        ),
+    ]
+}
+
+fn clock() -> impl FnMut() -> u64 {
+    let mut ms = 1000;
+    move || {
+        ms += 250;
+        ms
+    }
+}
+
+fn transactions(samples: &[[I2cTrans; 2]]) -> Vec<I2cTrans> {
+    let mut trans = vec![I2cTrans::write(ADDR, vec![Register::CONFIG1, 5])];
+    for s in samples {
+        trans.extend_from_slice(s);
+    }
+    trans
+}
+
+#[test]
+fn can_log_csv() {
+    let dir = directory("csv");
+    let mut sensor = new(&transactions(&[
+        sample(BF::CONVENF, 1, 2, 3),
+        sample(BF::BOUTF | BF::RGBTHF, 4, 5, 6),
+    ]));
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let config = LoggerConfig::new(Format::Csv).with_period(Duration::from_millis(0));
+    let mut logger = Logger::new(sensor, clock(), config, &dir, "log");
+    logger.run(Some(2)).unwrap();
+    assert_eq!(Some(dir.join("log-0000.csv")), logger.current_path());
+    destroy(logger.destroy().0);
+
+    let content = fs::read_to_string(dir.join("log-0000.csv")).unwrap();
+    assert_eq!(
+        "timestamp_ms,red,green,blue,resolution,range,mode,\
+         interrupt_triggered,conversion_completed,brownout\n\
+         1250,1,2,3,16,375,RedGreenBlue,0,1,0\n\
+         1500,4,5,6,16,375,RedGreenBlue,1,0,1\n",
+        content
+    );
+}
+
+#[test]
+fn can_log_json_lines_with_derived_values() {
+    let dir = directory("json");
+    let mut sensor = new(&transactions(&[sample(0, 0, 0, 0), sample(0, 0, 0x80, 0)]));
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let config = LoggerConfig::new(Format::JsonLines).with_derived_values(true);
+    let mut logger = Logger::new(sensor, clock(), config, &dir, "log");
+    logger.log().unwrap();
+    logger.log().unwrap();
+    destroy(logger.destroy().0);
+
+    let content = fs::read_to_string(dir.join("log-0000.jsonl")).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    assert_eq!(2, lines.len());
+    assert_eq!(
+        "{\"timestamp_ms\":1250,\"red\":0,\"green\":0,\"blue\":0,\"resolution\":16,\
+         \"range\":375,\"mode\":\"RedGreenBlue\",\"interrupt_triggered\":false,\
+         \"conversion_completed\":false,\"brownout\":false,\"lux\":0.00,\"cct\":null}",
+        lines[0]
+    );
+    assert!(lines[1].contains("\"green\":128,"));
+    assert!(lines[1].contains("\"lux\":0.73,"));
+}
+
+#[test]
+fn rotates_by_size() {
+    let dir = directory("size");
+    let samples: Vec<_> = (0..3).map(|i| sample(0, i, 0, 0)).collect();
+    let sensor = new(&transactions(&samples)[1..]);
+    let config = LoggerConfig::new(Format::JsonLines).with_rotation(Rotation::Size(0));
+    let mut logger = Logger::new(sensor, clock(), config, &dir, "log");
+    for _ in 0..3 {
+        logger.log().unwrap();
+    }
+    destroy(logger.destroy().0);
+    for i in 0..3 {
+        let content = fs::read_to_string(dir.join(format!("log-{:04}.jsonl", i))).unwrap();
+        assert_eq!(1, content.lines().count());
+        assert!(content.contains(&format!("\"red\":{},", i)));
+    }
+}
+
+#[test]
+fn rotates_by_time() {
+    let dir = directory("time");
+    let samples: Vec<_> = (0..5).map(|i| sample(0, i, 0, 0)).collect();
+    let sensor = new(&transactions(&samples)[1..]);
+    let config = LoggerConfig::new(Format::Csv)
+        .with_period(Duration::from_millis(0))
+        .with_rotation(Rotation::Interval(Duration::from_millis(500)));
+    let mut logger = Logger::new(sensor, clock(), config, &dir, "log");
+    logger.run(Some(5)).unwrap();
+    destroy(logger.destroy().0);
+    let count = |i: u32| {
+        fs::read_to_string(dir.join(format!("log-{:04}.csv", i)))
+            .unwrap()
+            .lines()
+            .count()
+    };
+    // Header plus records at 1250 and 1500, 1750 and 2000, and 2250
+    assert_eq!(3, count(0));
+    assert_eq!(3, count(1));
+    assert_eq!(2, count(2));
+    assert!(!dir.join("log-0003.csv").exists());
+}
+
+#[test]
+fn stops_on_device_error() {
+    let dir = directory("error");
+    let sensor = new(
+        &[I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![0])
+            .with_error(MockError::Io(ErrorKind::Other))],
+    );
+    let mut logger = Logger::new(sensor, clock(), LoggerConfig::default(), &dir, "log");
+    match logger.run(None) {
+        Err(LoggerError::Device(Error::I2C(_))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    assert_eq!(None, logger.current_path());
+    destroy(logger.destroy().0);
+}
+
+#[test]
+fn reports_io_errors() {
+    let dir = directory("io").join("missing");
+    let sensor = new(&sample(0, 0, 0, 0));
+    let mut logger = Logger::new(sensor, clock(), LoggerConfig::default(), &dir, "log");
+    match logger.log() {
+        Err(LoggerError::Io(_)) => (),
+        _ => panic!("Should return I/O error."),
+    }
+    destroy(logger.destroy().0);
+}
+
+#[test]
+fn does_not_overwrite_files_from_previous_runs() {
+    let dir = directory("restart");
+    for red in 1..=2 {
+        let mut sensor = new(&transactions(&[sample(0, red, 2, 3)]));
+        sensor
+            .set_operating_mode(OperatingMode::RedGreenBlue)
+            .unwrap();
+        let mut logger = Logger::new(sensor, clock(), LoggerConfig::new(Format::Csv), &dir, "log");
+        logger.log().unwrap();
+        let expected = dir.join(format!("log-{:04}.csv", red - 1));
+        assert_eq!(Some(expected), logger.current_path());
+        destroy(logger.destroy().0);
+    }
+    let first = fs::read_to_string(dir.join("log-0000.csv")).unwrap();
+    assert!(first.ends_with("1250,1,2,3,16,375,RedGreenBlue,0,0,0\n"));
+    let second = fs::read_to_string(dir.join("log-0001.csv")).unwrap();
+    assert!(second.ends_with("1250,2,2,3,16,375,RedGreenBlue,0,0,0\n"));
+}
+
+#[test]
+fn does_not_wait_after_last_sample() {
+    let dir = directory("last");
+    let mut sensor = new(&transactions(&[sample(0, 1, 2, 3)]));
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let config = LoggerConfig::new(Format::Csv).with_period(Duration::from_secs(3600));
+    let mut logger = Logger::new(sensor, clock(), config, &dir, "log");
+    logger.run(Some(1)).unwrap();
+    destroy(logger.destroy().0);
+}
@@ -0,0 +1,146 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{delay::NoopDelay, i2c::Transaction as I2cTrans, MockError};
+use isl29125::{measurements::Pacing, Error, InterruptPinMode, OperatingMode};
+use std::io::ErrorKind;
+
+fn read(red: u8) -> I2cTrans {
+    I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0, 0, red, 0, 0, 0])
+}
+
+fn read_error() -> I2cTrans {
+    I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+        .with_error(MockError::Io(ErrorKind::Other))
+}
+
+#[test]
+fn yields_measurements_paced_by_conversion_time() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        read(1),
+        read(2),
+        read(3),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let mut delay = NoopDelay;
+    let reds: Vec<u16> = sensor
+        .measurements(&mut delay, Pacing::ConversionTime)
+        .take(3)
+        .map(|m| m.unwrap().red)
+        .collect();
+    assert_eq!(vec![1, 2, 3], reds);
+    destroy(sensor);
+}
+
+#[test]
+fn yields_measurements_paced_by_conversion_done() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 2]),
+        I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![0]),
+        I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![BF::CONVENF]),
+        I2cTrans::write(ADDR, vec![Register::STATUS, 0]),
+        read(7),
+    ]);
+    sensor.set_operating_mode(OperatingMode::RedOnly).unwrap();
+    let pacing = Pacing::ConversionDone {
+        poll_interval_us: 1000,
+    };
+    let mut measurements = sensor.into_measurements(NoopDelay, pacing);
+    assert_eq!(7, measurements.next().unwrap().unwrap().red);
+    let (sensor, _delay) = measurements.into_inner();
+    destroy(sensor);
+}
+
+#[test]
+fn stops_after_error() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        read(1),
+        read_error(),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let mut measurements = sensor.into_measurements(NoopDelay, Pacing::default());
+    assert!(measurements.next().unwrap().is_ok());
+    match measurements.next() {
+        Some(Err(Error::I2C(_))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    assert!(measurements.next().is_none());
+    assert!(measurements.next().is_none());
+    destroy(measurements.into_inner().0);
+}
+
+#[test]
+fn stops_without_channels() {
+    let mut sensor = new(&[]);
+    let mut delay = NoopDelay;
+    let mut measurements = sensor.measurements(&mut delay, Pacing::ConversionTime);
+    match measurements.next() {
+        Some(Err(Error::InvalidInputData)) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    assert!(measurements.next().is_none());
+    destroy(sensor);
+}
+
+#[test]
+fn times_out_when_no_conversion_completes() {
+    let status = || I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![0]);
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 2]),
+        status(),
+        status(),
+        status(),
+    ]);
+    sensor.set_operating_mode(OperatingMode::RedOnly).unwrap();
+    let pacing = Pacing::ConversionDone {
+        poll_interval_us: 100_000,
+    };
+    let mut measurements = sensor.into_measurements(NoopDelay, pacing);
+    match measurements.next() {
+        Some(Err(Error::Timeout)) => (),
+        _ => panic!("Should return timeout error."),
+    }
+    assert!(measurements.next().is_none());
+    destroy(measurements.into_inner().0);
+}
+
+#[test]
+fn cannot_poll_without_interval() {
+    let mut sensor = new(&[I2cTrans::write(ADDR, vec![Register::CONFIG1, 2])]);
+    sensor.set_operating_mode(OperatingMode::RedOnly).unwrap();
+    let pacing = Pacing::ConversionDone {
+        poll_interval_us: 0,
+    };
+    let mut delay = NoopDelay;
+    match sensor.measurements(&mut delay, pacing).next() {
+        Some(Err(Error::InvalidInputData)) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_in_sync_start_mode() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 2]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::SYNC | 2]),
+    ]);
+    sensor.set_operating_mode(OperatingMode::RedOnly).unwrap();
+    sensor
+        .set_interrupt_pin_mode(InterruptPinMode::SyncStart)
+        .unwrap();
+    let pacing = Pacing::ConversionDone {
+        poll_interval_us: 1000,
+    };
+    let mut delay = NoopDelay;
+    match sensor.measurements(&mut delay, pacing).next() {
+        Some(Err(Error::InvalidInputData)) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
@@ -0,0 +1,163 @@
+#![cfg(feature = "std")]
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{i2c::Transaction as I2cTrans, MockError};
+use isl29125::{metrics::Exporter, Error};
+use std::{
+    fs,
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+fn status(value: u8) -> I2cTrans {
+    I2cTrans::write_read(ADDR, vec![Register::STATUS], vec![value])
+}
+
+fn read(red: u8, green: u8, blue: u8) -> I2cTrans {
+    I2cTrans::write_read(
+        ADDR,
+        vec![Register::GREEN_L],
+        vec![green, 0, red, 0, blue, 0],
+    )
+}
+
+#[test]
+fn renders_counters_before_first_update() {
+    let exporter = Exporter::new(new(&[]));
+    assert_eq!(
+        "# TYPE isl29125_brownouts counter\n\
+         # HELP isl29125_brownouts Brownout conditions detected.\n\
+         isl29125_brownouts_total 0\n\
+         # TYPE isl29125_i2c_errors counter\n\
+         # HELP isl29125_i2c_errors Updates failed due to I2C errors.\n\
+         isl29125_i2c_errors_total 0\n\
+         # EOF\n",
+        exporter.render()
+    );
+    destroy(exporter.destroy());
+}
+
+#[test]
+fn renders_measurement_with_labels() {
+    let sensor = new(&[status(0), read(1, 2, 3)]);
+    let mut exporter = Exporter::new(sensor)
+        .with_label("room", "green \"house\"")
+        .with_label("sensor", "a");
+    exporter.update().unwrap();
+    let text = exporter.render();
+    assert!(text.contains(
+        "isl29125_channel_counts{room=\"green \\\"house\\\"\",sensor=\"a\",channel=\"red\"} 1\n"
+    ));
+    assert!(text.contains("channel=\"green\"} 2\n"));
+    assert!(text.contains("channel=\"blue\"} 3\n"));
+    assert!(text.contains("# UNIT isl29125_illuminance_lux lux\n"));
+    assert!(text.contains("isl29125_illuminance_lux{room="));
+    assert!(text.contains("isl29125_cct_kelvin{room="));
+    assert!(text.ends_with("# EOF\n"));
+    destroy(exporter.destroy());
+}
+
+#[test]
+fn renders_measurement_in_prometheus_format() {
+    let sensor = new(&[status(0), read(1, 2, 3)]);
+    let mut exporter = Exporter::new(sensor);
+    exporter.update().unwrap();
+    let text = exporter.render_prometheus();
+    assert!(text.contains("# TYPE isl29125_channel_counts gauge\n"));
+    assert!(text.contains("isl29125_channel_counts{channel=\"red\"} 1\n"));
+    assert!(text.contains("# TYPE isl29125_illuminance_lux gauge\n"));
+    assert!(text.contains("# TYPE isl29125_brownouts_total counter\n"));
+    assert!(!text.contains("# UNIT"));
+    assert!(!text.contains("# EOF"));
+    destroy(exporter.destroy());
+}
+
+#[test]
+fn counts_and_clears_brownouts() {
+    let sensor = new(&[
+        status(BF::BOUTF),
+        I2cTrans::write(ADDR, vec![Register::STATUS, 0]),
+        read(0, 0, 0),
+        status(0),
+        read(0, 0, 0),
+    ]);
+    let mut exporter = Exporter::new(sensor);
+    exporter.update().unwrap();
+    exporter.update().unwrap();
+    assert_eq!(1, exporter.brownouts());
+    assert!(exporter.render().contains("isl29125_brownouts_total 1\n"));
+    assert!(!exporter.render().contains("isl29125_cct_kelvin"));
+    destroy(exporter.destroy());
+}
+
+#[test]
+fn counts_i2c_errors_and_keeps_last_measurement() {
+    let sensor = new(&[
+        status(0),
+        read(5, 0, 0),
+        status(0),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    let mut exporter = Exporter::new(sensor);
+    exporter.update().unwrap();
+    match exporter.update() {
+        Err(Error::I2C(_)) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    assert_eq!(1, exporter.i2c_errors());
+    let text = exporter.render();
+    assert!(text.contains("isl29125_i2c_errors_total 1\n"));
+    assert!(text.contains("isl29125_channel_counts{channel=\"red\"} 5\n"));
+    destroy(exporter.destroy());
+}
+
+#[test]
+fn writes_textfile() {
+    let dir = std::env::temp_dir().join(format!("isl29125-metrics-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("isl29125.prom");
+    let exporter = Exporter::new(new(&[]));
+    exporter.write_textfile(&path).unwrap();
+    assert_eq!(
+        "# TYPE isl29125_brownouts_total counter\n\
+         # HELP isl29125_brownouts_total Brownout conditions detected.\n\
+         isl29125_brownouts_total 0\n\
+         # TYPE isl29125_i2c_errors_total counter\n\
+         # HELP isl29125_i2c_errors_total Updates failed due to I2C errors.\n\
+         isl29125_i2c_errors_total 0\n",
+        fs::read_to_string(&path).unwrap()
+    );
+    assert!(!dir.join("isl29125.prom.tmp").exists());
+    destroy(exporter.destroy());
+}
+
+fn http_get<I2C>(exporter: &Exporter<I2C>, path: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+    let client = thread::spawn(move || {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    });
+    let (stream, _) = listener.accept().unwrap();
+    exporter.serve_http(stream).unwrap();
+    client.join().unwrap()
+}
+
+#[test]
+fn serves_metrics_over_http() {
+    let exporter = Exporter::new(new(&[]));
+    let response = http_get(&exporter, "/metrics");
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("Content-Type: application/openmetrics-text"));
+    assert!(response.ends_with(&exporter.render()));
+
+    let response = http_get(&exporter, "/other");
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    destroy(exporter.destroy());
+}
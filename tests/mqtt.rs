@@ -0,0 +1,262 @@
+#![cfg(feature = "std")]
+mod common;
+use crate::common::{destroy, new, Register, ADDR};
+use embedded_hal_mock::eh0::{i2c::Transaction as I2cTrans, MockError};
+use isl29125::{
+    mqtt::{MqttConfig, MqttError, Publisher},
+    Error,
+};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+struct Packet {
+    header: u8,
+    body: Vec<u8>,
+}
+
+impl Packet {
+    fn publish(&self) -> (String, String, bool) {
+        assert_eq!(0x30, self.header & 0xF0);
+        let len = usize::from(u16::from_be_bytes([self.body[0], self.body[1]]));
+        let topic = String::from_utf8(self.body[2..2 + len].to_vec()).unwrap();
+        let payload = String::from_utf8(self.body[2 + len..].to_vec()).unwrap();
+        (topic, payload, self.header & 1 != 0)
+    }
+}
+
+fn read_packet(stream: &mut TcpStream) -> Option<Packet> {
+    let mut header = [0];
+    if stream.read(&mut header).ok()? == 0 {
+        return None;
+    }
+    let mut length = 0;
+    let mut shift = 0;
+    loop {
+        let mut byte = [0];
+        stream.read_exact(&mut byte).ok()?;
+        length |= usize::from(byte[0] & 0x7F) << shift;
+        shift += 7;
+        if byte[0] & 0x80 == 0 {
+            break;
+        }
+    }
+    let mut body = vec![0; length];
+    stream.read_exact(&mut body).ok()?;
+    Some(Packet {
+        header: header[0],
+        body,
+    })
+}
+
+/// Accept one connection, answer CONNECT with `return_code` and PINGREQ with
+/// PINGRESP and collect all packets until the connection is closed or
+/// DISCONNECT is received.
+fn fake_broker(return_code: u8) -> (u16, JoinHandle<Vec<Packet>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let handle = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut packets = Vec::new();
+        let connect = read_packet(&mut stream).unwrap();
+        assert_eq!(0x10, connect.header);
+        stream.write_all(&[0x20, 2, 0, return_code]).unwrap();
+        packets.push(connect);
+        while let Some(packet) = read_packet(&mut stream) {
+            let header = packet.header;
+            if header == 0xC0 {
+                stream.write_all(&[0xD0, 0]).unwrap();
+            }
+            packets.push(packet);
+            if header == 0xE0 {
+                break;
+            }
+        }
+        packets
+    });
+    (port, handle)
+}
+
+fn read(red: u8, green: u8, blue: u8) -> I2cTrans {
+    I2cTrans::write_read(
+        ADDR,
+        vec![Register::GREEN_L],
+        vec![green, 0, red, 0, blue, 0],
+    )
+}
+
+#[test]
+fn publishes_values() {
+    let (port, broker) = fake_broker(0);
+    let sensor = new(&[read(1, 2, 3), read(0, 0, 0)]);
+    let config = MqttConfig::new("127.0.0.1", "sensor1").with_port(port);
+    let mut publisher = Publisher::new(sensor, config);
+    publisher.connect().unwrap();
+    publisher.publish().unwrap();
+    publisher.publish().unwrap();
+    publisher.disconnect().unwrap();
+    destroy(publisher.destroy());
+
+    let packets = broker.join().unwrap();
+    let publishes: Vec<_> = packets[1..packets.len() - 1]
+        .iter()
+        .map(Packet::publish)
+        .collect();
+    let topic = |key: &str| format!("isl29125/sensor1/{}", key);
+    assert_eq!((topic("red"), "1".into(), false), publishes[0]);
+    assert_eq!((topic("green"), "2".into(), false), publishes[1]);
+    assert_eq!((topic("blue"), "3".into(), false), publishes[2]);
+    assert_eq!(topic("illuminance"), publishes[3].0);
+    assert_eq!(topic("color_temperature"), publishes[4].0);
+    // No color temperature for a dark measurement
+    assert_eq!(9, publishes.len());
+    assert_eq!((topic("illuminance"), "0.0".into(), false), publishes[8]);
+    assert_eq!(0xE0, packets.last().unwrap().header);
+}
+
+#[test]
+fn publishes_discovery_messages() {
+    let (port, broker) = fake_broker(0);
+    let config = MqttConfig::new("127.0.0.1", "sensor1")
+        .with_port(port)
+        .with_base_topic("home/light")
+        .with_device_name("Living room");
+    let mut publisher = Publisher::new(new(&[]), config);
+    publisher.connect().unwrap();
+    publisher.publish_discovery().unwrap();
+    publisher.disconnect().unwrap();
+    destroy(publisher.destroy());
+
+    let packets = broker.join().unwrap();
+    let publishes: Vec<_> = packets[1..packets.len() - 1]
+        .iter()
+        .map(Packet::publish)
+        .collect();
+    assert_eq!(5, publishes.len());
+    assert!(publishes.iter().all(|(_, _, retain)| *retain));
+    let (topic, payload, _) = &publishes[3];
+    assert_eq!("homeassistant/sensor/sensor1/illuminance/config", topic);
+    assert_eq!(
+        "{\"name\":\"Illuminance\",\"unique_id\":\"sensor1_illuminance\",\
+         \"state_topic\":\"home/light/illuminance\",\"state_class\":\"measurement\",\
+         \"unit_of_measurement\":\"lx\",\"device_class\":\"illuminance\",\
+         \"device\":{\"identifiers\":[\"sensor1\"],\"name\":\"Living room\",\
+         \"model\":\"ISL29125\",\"manufacturer\":\"Renesas\"}}",
+        payload
+    );
+    assert_eq!(
+        "homeassistant/sensor/sensor1/color_temperature/config",
+        publishes[4].0
+    );
+}
+
+#[test]
+fn sends_credentials() {
+    let (port, broker) = fake_broker(0);
+    let config = MqttConfig::new("127.0.0.1", "id")
+        .with_port(port)
+        .with_credentials("user", "secret");
+    let mut publisher = Publisher::new(new(&[]), config);
+    publisher.connect().unwrap();
+    publisher.disconnect().unwrap();
+    destroy(publisher.destroy());
+    let connect = &broker.join().unwrap()[0];
+    assert_eq!(0xC2, connect.body[7]);
+    assert!(connect.body.ends_with(b"\0\x04user\0\x06secret"));
+}
+
+#[test]
+fn fails_when_connection_refused() {
+    let (port, broker) = fake_broker(5);
+    let config = MqttConfig::new("127.0.0.1", "id").with_port(port);
+    let mut publisher = Publisher::new(new(&[]), config);
+    match publisher.connect() {
+        Err(e) => assert_eq!(ErrorKind::ConnectionRefused, e.kind()),
+        Ok(_) => panic!("Should fail."),
+    }
+    assert!(!publisher.is_connected());
+    destroy(publisher.destroy());
+    broker.join().unwrap();
+}
+
+#[test]
+fn fails_to_publish_when_not_connected() {
+    let mut publisher = Publisher::new(new(&[]), MqttConfig::new("127.0.0.1", "id"));
+    match publisher.publish_discovery() {
+        Err(e) => assert_eq!(ErrorKind::NotConnected, e.kind()),
+        Ok(_) => panic!("Should fail."),
+    }
+    destroy(publisher.destroy());
+}
+
+#[test]
+fn returns_device_errors() {
+    let (port, broker) = fake_broker(0);
+    let sensor = new(&[
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    let config = MqttConfig::new("127.0.0.1", "id").with_port(port);
+    let mut publisher = Publisher::new(sensor, config);
+    publisher.connect().unwrap();
+    match publisher.publish() {
+        Err(MqttError::Device(Error::I2C(_))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    publisher.disconnect().unwrap();
+    destroy(publisher.destroy());
+    assert_eq!(2, broker.join().unwrap().len());
+}
+
+#[test]
+fn can_ping() {
+    let (port, broker) = fake_broker(0);
+    let config = MqttConfig::new("127.0.0.1", "id").with_port(port);
+    let mut publisher = Publisher::new(new(&[]), config);
+    publisher.connect().unwrap();
+    publisher.ping().unwrap();
+    // Nothing is sent before the keep alive interval has elapsed.
+    publisher.ping_if_idle().unwrap();
+    publisher.disconnect().unwrap();
+    destroy(publisher.destroy());
+    let headers: Vec<_> = broker.join().unwrap().iter().map(|p| p.header).collect();
+    assert_eq!(vec![0x10, 0xC0, 0xE0], headers);
+}
+
+#[test]
+fn pings_when_idle() {
+    let (port, broker) = fake_broker(0);
+    let config = MqttConfig::new("127.0.0.1", "id")
+        .with_port(port)
+        .with_keep_alive(1);
+    let mut publisher = Publisher::new(new(&[]), config);
+    publisher.connect().unwrap();
+    thread::sleep(Duration::from_millis(1000));
+    publisher.ping_if_idle().unwrap();
+    publisher.ping_if_idle().unwrap();
+    publisher.disconnect().unwrap();
+    destroy(publisher.destroy());
+    let headers: Vec<_> = broker.join().unwrap().iter().map(|p| p.header).collect();
+    assert_eq!(vec![0x10, 0xC0, 0xE0], headers);
+}
+
+#[test]
+fn times_out_when_broker_does_not_answer() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let config = MqttConfig::new("127.0.0.1", "id")
+        .with_port(port)
+        .with_timeout(Duration::from_millis(100));
+    let mut publisher = Publisher::new(new(&[]), config);
+    let start = Instant::now();
+    publisher
+        .connect()
+        .expect_err("Should fail without an answer to CONNECT.");
+    assert!(start.elapsed() < Duration::from_secs(5));
+    assert!(!publisher.is_connected());
+    destroy(publisher.destroy());
+}
@@ -0,0 +1,144 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::i2c::Transaction as I2cTrans;
+use isl29125::{ChannelCondition as CC, Error, OperatingMode, Range};
+
+const RGB_MODE: u8 = 5;
+
+fn le(red: u16, green: u16, blue: u16) -> Vec<u8> {
+    let mut data = Vec::new();
+    for v in [green, red, blue].iter() {
+        data.extend_from_slice(&v.to_le_bytes());
+    }
+    data
+}
+
+fn read(red: u16, green: u16, blue: u16) -> I2cTrans {
+    I2cTrans::write_read(ADDR, vec![Register::GREEN_L], le(red, green, blue))
+}
+
+#[test]
+fn can_read_normal_measurement() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        read(1000, 2000, 3000),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let checked = sensor.read_checked().unwrap();
+    assert_eq!(1000, checked.measurement.red);
+    assert_eq!(
+        (CC::Normal, CC::Normal, CC::Normal),
+        (checked.red, checked.green, checked.blue)
+    );
+    assert!(!checked.is_saturated());
+    assert_eq!(None, checked.recommended_range);
+    destroy(sensor);
+}
+
+#[test]
+fn recommends_higher_range_when_saturated() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        read(65535, 100, 3000),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let checked = sensor.read_checked().unwrap();
+    assert_eq!(
+        (CC::Saturated, CC::UnderRange),
+        (checked.red, checked.green)
+    );
+    assert!(checked.is_saturated());
+    assert!(checked.is_under_range());
+    assert_eq!(Some(Range::Lux10000), checked.recommended_range);
+    destroy(sensor);
+}
+
+#[test]
+fn returns_error_when_saturated_at_highest_range() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE | RGB_MODE]),
+        read(1000, 65535, 3000),
+    ]);
+    sensor.set_range(Range::Lux10000).unwrap();
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.read_checked() {
+        Err(Error::Saturated) => (),
+        _ => panic!("Should return saturated error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn recommends_lower_range_for_low_light() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RANGE | RGB_MODE]),
+        read(1000, 1500, 800),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor.set_range(Range::Lux10000).unwrap();
+    let checked = sensor.read_checked().unwrap();
+    assert_eq!(Some(Range::Lux375), checked.recommended_range);
+    destroy(sensor);
+}
+
+#[test]
+fn ignores_disabled_channels_for_recommendation() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 2]),
+        read(1000, 65535, 65535),
+    ]);
+    sensor.set_operating_mode(OperatingMode::RedOnly).unwrap();
+    let checked = sensor.read_checked().unwrap();
+    assert_eq!(CC::Normal, checked.red);
+    assert_eq!(CC::Disabled, checked.green);
+    assert_eq!(CC::Disabled, checked.blue);
+    assert!(!checked.is_saturated());
+    assert_eq!(None, checked.recommended_range);
+    destroy(sensor);
+}
+
+#[test]
+fn checks_against_12_bit_resolution() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::RESOLUTION | RGB_MODE]),
+        read(4095, 2000, 10),
+    ]);
+    sensor.set_resolution(isl29125::Resolution::Bit12).unwrap();
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let checked = sensor.read_checked().unwrap();
+    assert_eq!(
+        (CC::Saturated, CC::Normal, CC::UnderRange),
+        (checked.red, checked.green, checked.blue)
+    );
+    destroy(sensor);
+}
+
+#[test]
+fn disabled_channels_are_not_under_range() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 1]),
+        read(0, 1000, 0),
+    ]);
+    sensor.set_operating_mode(OperatingMode::GreenOnly).unwrap();
+    let checked = sensor.read_checked().unwrap();
+    assert_eq!(
+        (CC::Disabled, CC::Normal, CC::Disabled),
+        (checked.red, checked.green, checked.blue)
+    );
+    assert!(!checked.is_under_range());
+    assert!(!checked.is_saturated());
+    destroy(sensor);
+}
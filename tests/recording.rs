@@ -0,0 +1,132 @@
+#![cfg(feature = "std")]
+mod common;
+use crate::common::{Register, ADDR};
+use embedded_hal_mock::eh0::{
+    i2c::{Mock as I2cMock, Transaction as I2cTrans},
+    MockError,
+};
+use isl29125::{
+    recording::{ParseError, Recorder, ReplayError, Replayer, Transaction},
+    Error, Isl29125, OperatingMode, Resolution,
+};
+use std::io::ErrorKind;
+
+const LOG: &str = "\
+W 44 0105
+WR 44 09 d20416092e16
+WR 44 00 error 1
+";
+
+fn record() -> String {
+    let mock = I2cMock::new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0xd2, 0x04, 0x16, 0x09, 0x2e, 0x16],
+        ),
+        I2cTrans::write_read(ADDR, vec![Register::DEVICE_ID], vec![0])
+            .with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    let mut sensor = Isl29125::new(Recorder::new(mock, Vec::new()));
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor.read().unwrap();
+    assert!(sensor.device_id().is_err());
+    let (mut mock, log) = sensor.destroy().into_inner();
+    mock.done();
+    String::from_utf8(log).unwrap()
+}
+
+#[test]
+fn records_transactions() {
+    assert_eq!(LOG, record());
+}
+
+#[test]
+fn replays_recording() {
+    let mut sensor = Isl29125::new(Replayer::parse(&record()).unwrap());
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let m = sensor.read().unwrap();
+    assert_eq!((0x0916, 0x04d2, 0x162e), (m.red, m.green, m.blue));
+    assert_eq!(Resolution::Bit16, m.resolution);
+    match sensor.device_id() {
+        Err(Error::I2C(ReplayError::Recorded)) => (),
+        _ => panic!("Should return recorded error."),
+    }
+    let replayer = sensor.destroy();
+    assert!(replayer.is_finished());
+    assert_eq!(3, replayer.position());
+}
+
+#[test]
+fn detects_divergence() {
+    let mut sensor = Isl29125::new(Replayer::parse(LOG).unwrap());
+    match sensor.set_operating_mode(OperatingMode::RedOnly) {
+        Err(Error::I2C(ReplayError::Diverged(d))) => {
+            assert_eq!(0, d.index);
+            assert_eq!(
+                Some(Transaction::Write {
+                    address: ADDR,
+                    bytes: vec![Register::CONFIG1, 5],
+                    failed: false,
+                }),
+                d.expected
+            );
+            assert_eq!(
+                "transaction 0: got `W 44 0102`, expected `W 44 0105`",
+                d.to_string()
+            );
+        }
+        _ => panic!("Should diverge."),
+    }
+    // Divergence is sticky
+    assert!(sensor.read().is_err());
+    let replayer = sensor.destroy();
+    assert!(!replayer.is_finished());
+    assert_eq!(0, replayer.divergence().unwrap().index);
+}
+
+#[test]
+fn detects_read_length_divergence() {
+    let mut sensor = Isl29125::new(Replayer::parse("W 44 0105\nWR 44 09 d204\n").unwrap());
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.read() {
+        Err(Error::I2C(ReplayError::Diverged(d))) => assert_eq!(1, d.index),
+        _ => panic!("Should diverge."),
+    }
+    sensor.destroy();
+}
+
+#[test]
+fn detects_end_of_recording() {
+    let mut sensor = Isl29125::new(Replayer::parse("# Only one write\nW 44 0105\n").unwrap());
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match sensor.read() {
+        Err(Error::I2C(ReplayError::Diverged(d))) => {
+            assert_eq!(1, d.index);
+            assert_eq!(None, d.expected);
+            assert_eq!(
+                "transaction 1: got `WR 44 09 000000000000`, expected end of recording",
+                d.to_string()
+            );
+        }
+        _ => panic!("Should diverge."),
+    }
+    sensor.destroy();
+}
+
+#[test]
+fn reports_parse_errors() {
+    match Replayer::parse("W 44 0105\n\nWR 44 zz 00\n") {
+        Err(e) => assert_eq!(ParseError { line: 3 }, e),
+        Ok(_) => panic!("Should fail."),
+    }
+}
@@ -0,0 +1,82 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{delay::NoopDelay, i2c::Transaction as I2cTrans, MockError};
+use isl29125::{scheduler::Scheduler, Error, InterruptPinMode, OperatingMode};
+use std::io::ErrorKind;
+
+#[test]
+fn measures_and_powers_down() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0x78, 0x56, 0x34, 0x12, 0xBC, 0x9A],
+        ),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 0]),
+    ]);
+    let scheduler = Scheduler::new(OperatingMode::RedGreenBlue, 1_000_000);
+    let m = scheduler
+        .measure_and_wait(&mut sensor, &mut NoopDelay)
+        .unwrap();
+    assert_eq!((0x1234, 0x5678, 0x9ABC), (m.red, m.green, m.blue));
+    destroy(sensor);
+}
+
+#[test]
+fn powers_down_after_read_error() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 2]),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 0]),
+    ]);
+    let scheduler = Scheduler::new(OperatingMode::RedOnly, 1_000_000);
+    match scheduler.measure(&mut sensor, &mut NoopDelay) {
+        Err(Error::I2C(_)) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_without_channels() {
+    let mut sensor = new(&[]);
+    let scheduler = Scheduler::new(OperatingMode::StandBy, 1_000_000);
+    match scheduler.measure(&mut sensor, &mut NoopDelay) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn returns_read_error_before_power_down_error() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 2]),
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 0])
+            .with_error(MockError::Io(ErrorKind::TimedOut)),
+    ]);
+    let scheduler = Scheduler::new(OperatingMode::RedOnly, 1_000_000);
+    match scheduler.measure(&mut sensor, &mut NoopDelay) {
+        Err(Error::I2C(MockError::Io(ErrorKind::Other))) => (),
+        _ => panic!("Should return read error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_measure_in_sync_start_mode() {
+    let mut sensor = new(&[I2cTrans::write(ADDR, vec![Register::CONFIG1, BF::SYNC])]);
+    sensor
+        .set_interrupt_pin_mode(InterruptPinMode::SyncStart)
+        .unwrap();
+    let scheduler = Scheduler::new(OperatingMode::RedOnly, 1_000_000);
+    match scheduler.measure(&mut sensor, &mut NoopDelay) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
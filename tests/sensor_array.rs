@@ -0,0 +1,112 @@
+mod common;
+use crate::common::{Register, ADDR};
+use embedded_hal_mock::eh0::{
+    i2c::{Mock as I2cMock, Transaction as I2cTrans},
+    MockError,
+};
+use isl29125::{
+    sensor_array::{SensorArray, DEFAULT_MUX_ADDRESS as MUX},
+    Error, OperatingMode, SensorConfig,
+};
+use std::io::ErrorKind;
+
+fn new<const N: usize>(channels: [u8; N], transactions: &[I2cTrans]) -> SensorArray<I2cMock, N> {
+    SensorArray::new(I2cMock::new(transactions), MUX, channels)
+}
+
+fn destroy<const N: usize>(array: SensorArray<I2cMock, N>) {
+    array.destroy().done();
+}
+
+fn select(channel: u8) -> I2cTrans {
+    I2cTrans::write(MUX, vec![1 << channel])
+}
+
+fn read(data: Vec<u8>) -> I2cTrans {
+    I2cTrans::write_read(ADDR, vec![Register::GREEN_L], data)
+}
+
+#[test]
+fn can_create_and_destroy() {
+    let array = new([0, 1, 2], &[]);
+    assert_eq!(3, array.len());
+    destroy(array);
+}
+
+#[test]
+fn can_configure_all() {
+    let config = SensorConfig {
+        operating_mode: OperatingMode::RedGreenBlue,
+        ..SensorConfig::default()
+    };
+    let mut transactions = Vec::new();
+    for channel in [2, 5].iter() {
+        transactions.push(select(*channel));
+        transactions.push(I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]));
+        transactions.push(select(*channel));
+        transactions.push(I2cTrans::write(ADDR, vec![Register::CONFIG2, 0]));
+        transactions.push(select(*channel));
+        transactions.push(I2cTrans::write(ADDR, vec![Register::CONFIG3, 0]));
+    }
+    let mut array = new([2, 5], &transactions);
+    let results = array.configure(&config);
+    assert!(results.iter().all(Result::is_ok));
+    destroy(array);
+}
+
+#[test]
+fn can_read_all_reporting_errors_per_sensor() {
+    let mut array = new(
+        [0, 7, 8, 3],
+        &[
+            select(0),
+            read(vec![1, 0, 2, 0, 3, 0]),
+            select(7).with_error(MockError::Io(ErrorKind::Other)),
+            select(3),
+            read(vec![4, 0, 5, 0, 6, 0]),
+        ],
+    );
+    let results = array.read_all();
+    let m = results[0].as_ref().unwrap();
+    assert_eq!((2, 1, 3), (m.red, m.green, m.blue));
+    match results[1] {
+        Err(Error::I2C(MockError::Io(ErrorKind::Other))) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    match results[2] {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    let m = results[3].as_ref().unwrap();
+    assert_eq!((5, 4, 6), (m.red, m.green, m.blue));
+    destroy(array);
+}
+
+#[test]
+fn keeps_sensor_state_between_operations() {
+    let mut array = new(
+        [1],
+        &[
+            select(1),
+            I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+            select(1),
+            I2cTrans::write(ADDR, vec![Register::CONFIG1, 0x10 | 5]),
+        ],
+    );
+    array
+        .with_sensor(0, |s| s.set_operating_mode(OperatingMode::RedGreenBlue))
+        .unwrap();
+    array
+        .with_sensor(0, |s| s.set_resolution(isl29125::Resolution::Bit12))
+        .unwrap();
+    destroy(array);
+}
+
+#[test]
+fn cannot_use_sensor_out_of_bounds() {
+    let mut array = new([1], &[]);
+    array
+        .with_sensor(1, |s| s.read())
+        .expect_err("Should return error.");
+    destroy(array);
+}
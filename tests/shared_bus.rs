@@ -0,0 +1,70 @@
+mod common;
+use crate::common::{Register, ADDR};
+use embedded_hal::blocking::i2c;
+use embedded_hal_mock::eh0::i2c::{Mock as I2cMock, Transaction as I2cTrans};
+use isl29125::{BorrowedI2c, Isl29125, OperatingMode};
+use shared_bus::BusManagerSimple;
+
+const EEPROM_ADDR: u8 = 0x50;
+
+/// Minimal driver for an EEPROM sharing the bus with the sensor.
+struct Eeprom<I2C>(I2C);
+
+impl<I2C, E> Eeprom<I2C>
+where
+    I2C: i2c::WriteRead<Error = E> + i2c::Write<Error = E>,
+{
+    fn read_byte(&mut self, address: u8) -> Result<u8, E> {
+        let mut data = [0];
+        self.0.write_read(EEPROM_ADDR, &[address], &mut data)?;
+        Ok(data[0])
+    }
+
+    fn write_byte(&mut self, address: u8, value: u8) -> Result<(), E> {
+        self.0.write(EEPROM_ADDR, &[address, value])
+    }
+}
+
+#[test]
+fn can_share_bus_with_other_driver() {
+    let mut mock = I2cMock::new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        I2cTrans::write_read(EEPROM_ADDR, vec![0x00], vec![0xAB]),
+        I2cTrans::write_read(ADDR, vec![Register::RED_L], vec![0x34, 0x12]),
+        I2cTrans::write(EEPROM_ADDR, vec![0x00, 0x12]),
+    ]);
+    let bus = BusManagerSimple::new(mock.clone());
+    let mut sensor = Isl29125::new(bus.acquire_i2c());
+    let mut eeprom = Eeprom(bus.acquire_i2c());
+
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    assert_eq!(0xAB, eeprom.read_byte(0x00).unwrap());
+    let red = sensor.red().unwrap();
+    assert_eq!(0x1234, red);
+    eeprom.write_byte(0x00, 0x12).unwrap();
+
+    sensor.destroy();
+    mock.done();
+}
+
+#[test]
+fn can_use_borrowed_bus() {
+    let mut mock = I2cMock::new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        I2cTrans::write(EEPROM_ADDR, vec![0x00, 0x12]),
+        I2cTrans::write_read(ADDR, vec![Register::RED_L], vec![0x34, 0x12]),
+    ]);
+    {
+        let mut sensor = Isl29125::new(BorrowedI2c(&mut mock));
+        sensor
+            .set_operating_mode(OperatingMode::RedGreenBlue)
+            .unwrap();
+    }
+    i2c::Write::write(&mut mock, EEPROM_ADDR, &[0x00, 0x12]).unwrap();
+    let mut sensor = Isl29125::new(BorrowedI2c(&mut mock));
+    assert_eq!(0x1234, sensor.red().unwrap());
+    sensor.destroy();
+    mock.done();
+}
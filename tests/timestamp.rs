@@ -0,0 +1,70 @@
+mod common;
+use crate::common::{destroy, new, Register, ADDR};
+use embedded_hal_mock::eh0::{i2c::Transaction as I2cTrans, MockError};
+use isl29125::{timestamp::Timestamped, Error, Measurement, OperatingMode, Range, Resolution};
+use std::io::ErrorKind;
+
+fn read(red: u8) -> I2cTrans {
+    I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0, 0, red, 0, 0, 0])
+}
+
+#[test]
+fn can_read_timestamped_measurements() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5]),
+        read(1),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, 5 | 0b1000]),
+        read(2),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let mut ticks = 10_u32;
+    let mut sensor = Timestamped::new(sensor, move || {
+        ticks += 1;
+        ticks
+    });
+
+    let m = sensor.read().unwrap();
+    assert_eq!(11, m.timestamp);
+    assert_eq!(Measurement::new(1, 0, 0, Resolution::Bit16), m.measurement);
+    assert_eq!(Range::Lux375, m.range);
+    assert_eq!(OperatingMode::RedGreenBlue, m.operating_mode);
+    assert_eq!(Resolution::Bit16, m.resolution());
+
+    sensor.sensor().set_range(Range::Lux10000).unwrap();
+    let m = sensor.read().unwrap();
+    assert_eq!(12, m.timestamp);
+    assert_eq!(Range::Lux10000, m.range);
+
+    destroy(sensor.destroy().0);
+}
+
+#[test]
+fn does_not_timestamp_errors() {
+    let sensor = new(&[
+        I2cTrans::write_read(ADDR, vec![Register::GREEN_L], vec![0; 6])
+            .with_error(MockError::Io(ErrorKind::Other)),
+    ]);
+    let mut calls = 0;
+    let mut sensor = Timestamped::new(sensor, || {
+        calls += 1;
+    });
+    match sensor.read() {
+        Err(Error::I2C(_)) => (),
+        _ => panic!("Should return I2C error."),
+    }
+    destroy(sensor.destroy().0);
+    assert_eq!(0, calls);
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn can_use_std_clock() {
+    use isl29125::timestamp::StdClock;
+    let mut sensor = Timestamped::new(new(&[read(1), read(2)]), StdClock);
+    let first = sensor.read().unwrap();
+    let second = sensor.read().unwrap();
+    assert!(second.timestamp >= first.timestamp);
+    destroy(sensor.destroy().0);
+}
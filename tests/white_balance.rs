@@ -0,0 +1,83 @@
+mod common;
+use crate::common::{destroy, new, BitFlags as BF, Register, ADDR};
+use embedded_hal_mock::eh0::{delay::NoopDelay, i2c::Transaction as I2cTrans};
+use isl29125::{
+    white_balance::WhiteBalance, Error, InterruptPinMode, Measurement, OperatingMode, Resolution,
+};
+
+const RGB_MODE: u8 = 5;
+
+#[test]
+fn can_capture_white_reference() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0x00, 0x30, 0x00, 0x10, 0x00, 0x08],
+        ),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0x00, 0x50, 0x00, 0x30, 0x00, 0x18],
+        ),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    let wb = WhiteBalance::capture(&mut sensor, &mut NoopDelay, 2).unwrap();
+    let white = Measurement::new(0x2000, 0x4000, 0x1000, Resolution::Bit16);
+    assert_eq!(WhiteBalance::from_reference(&white).unwrap(), wb);
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_capture_outside_rgb_mode() {
+    let mut sensor = new(&[]);
+    match WhiteBalance::capture(&mut sensor, &mut NoopDelay, 1) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_capture_in_sync_start_mode() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE | BF::SYNC]),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    sensor
+        .set_interrupt_pin_mode(InterruptPinMode::SyncStart)
+        .unwrap();
+    match WhiteBalance::capture(&mut sensor, &mut NoopDelay, 1) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}
+
+#[test]
+fn cannot_capture_reference_without_blue() {
+    let mut sensor = new(&[
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write(ADDR, vec![Register::CONFIG1, RGB_MODE]),
+        I2cTrans::write_read(
+            ADDR,
+            vec![Register::GREEN_L],
+            vec![0x00, 0x30, 0x00, 0x10, 0x00, 0x00],
+        ),
+    ]);
+    sensor
+        .set_operating_mode(OperatingMode::RedGreenBlue)
+        .unwrap();
+    match WhiteBalance::capture(&mut sensor, &mut NoopDelay, 1) {
+        Err(Error::InvalidInputData) => (),
+        _ => panic!("Should return invalid input data error."),
+    }
+    destroy(sensor);
+}